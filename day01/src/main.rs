@@ -1,4 +1,5 @@
-use anyhow::Result;
+use anyhow::{format_err, Result};
+use std::fs::File;
 use std::io::{self, prelude::*};
 use std::str::FromStr;
 
@@ -29,23 +30,46 @@ fn fuel_for_fuel(mut fuel: u64) -> u64 {
     }
 }
 
+/// Sum the fuel for every mass two ways in one pass:
+/// for the modules alone, and with the fuel's own fuel included.
+fn total_fuel(mut masses: impl Iterator<Item = Result<String>>) -> Result<(u64, u64)> {
+    masses.try_fold((0, 0), |(modules, with_fuel), mass| -> Result<(u64, u64)> {
+        let fuel = convert_mass_to_fuel(convert_mass_str(&mass?)?);
+        Ok((modules + fuel, with_fuel + fuel_for_fuel(fuel)))
+    })
+}
+
 fn main() -> Result<()> {
+    // read from the file named by the first argument, or stdin if there isn't one
     let stdin = std::io::stdin();
-    let masses = load_masses(stdin.lock());
-
-    let total = masses
-        .map(|mass| -> Result<u64> {
-            let mass = convert_mass_str(&mass?)?;
-            let fuel = convert_mass_to_fuel(mass);
-            if cfg!(feature="part2") {
-                Ok(fuel_for_fuel(fuel))
-            } else {
-                Ok(fuel)
-            }
-        })
-        .sum::<Result<u64>>()?;
-
-    println!("Total is {}", total);
+    let reader: Box<dyn Read + '_> = match std::env::args().nth(1) {
+        Some(path) => Box::new(
+            File::open(&path).map_err(|e| format_err!("Couldn't open {:?}: {}", path, e))?,
+        ),
+        None => Box::new(stdin.lock()),
+    };
+
+    let (modules, with_fuel) = total_fuel(load_masses(reader))?;
+
+    // part2 still prints just its own answer, for scripts that expect one number
+    if cfg!(feature = "part2") {
+        println!("Total is {}", with_fuel);
+    } else {
+        println!("Part 1: {}\nPart 2: {}", modules, with_fuel);
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn examples() {
+        let masses = load_masses(Cursor::new("12\n14\n1969\n100756\n"));
+
+        assert_eq!(total_fuel(masses).unwrap(), (34241, 51316));
+    }
+}
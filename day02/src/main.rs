@@ -1,29 +1,11 @@
-use anyhow;
+use anyhow::{self, format_err};
 use intcode::*;
 
-fn fix_1202(program: &mut Vec<usize>) {
+fn fix_1202(program: &mut [isize]) {
     program[1] = 12;
     program[2] = 2;
 }
 
-fn find_ad_nauseum(initial_program: Vec<usize>, target: usize) -> (usize, usize) {
-    for noun in 0..99 {
-        for verb in 0..99 {
-            let mut new_program = initial_program.clone();
-            new_program[1] = noun;
-            new_program[2] = verb;
-
-            let mut cpu = IntcodeComputer::new(new_program);
-
-            match cpu.execute() {
-                Ok(()) if cpu.memory[0] == target => return (noun, verb),
-                _ => continue,
-            }
-        }
-    }
-    panic!("no input works!");
-}
-
 fn main() -> anyhow::Result<()> {
     let mut program = stdin_to_prog()?;
 
@@ -32,11 +14,10 @@ fn main() -> anyhow::Result<()> {
 
         let mut cpu = IntcodeComputer::new(program);
 
-        let _ = cpu.execute();
-
-        println!("{}", cpu.memory[0]);
+        println!("{}", cpu.run_for_result()?);
     } else {
-        let (noun, verb) = find_ad_nauseum(program, 19690720);
+        let (noun, verb) = search_inputs(&program, 1, 2, 0, 19690720, 0..=99)
+            .ok_or_else(|| format_err!("no input works!"))?;
 
         println!("{}", (100 * noun) + verb);
     }
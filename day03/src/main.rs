@@ -25,6 +25,10 @@ enum Direction {
     D(usize),
     L(usize),
     R(usize),
+    NE(usize),
+    NW(usize),
+    SE(usize),
+    SW(usize),
 }
 
 impl Direction {
@@ -35,6 +39,22 @@ impl Direction {
             D(_) => coordinate.1 -= 1,
             L(_) => coordinate.0 -= 1,
             R(_) => coordinate.0 += 1,
+            NE(_) => {
+                coordinate.0 += 1;
+                coordinate.1 += 1;
+            }
+            NW(_) => {
+                coordinate.0 -= 1;
+                coordinate.1 += 1;
+            }
+            SE(_) => {
+                coordinate.0 += 1;
+                coordinate.1 -= 1;
+            }
+            SW(_) => {
+                coordinate.0 -= 1;
+                coordinate.1 -= 1;
+            }
         }
     }
 
@@ -46,6 +66,10 @@ impl Direction {
             D(x) => x,
             L(x) => x,
             R(x) => x,
+            NE(x) => x,
+            NW(x) => x,
+            SE(x) => x,
+            SW(x) => x,
         }
     }
 }
@@ -54,17 +78,45 @@ impl FromStr for Direction {
     type Err = anyhow::Error;
     fn from_str(string: &str) -> anyhow::Result<Direction> {
         use Direction::*;
-        let num = usize::from_str(&string[1..])?;
-        Ok(match &string[0..1] {
+        // cardinal directions are one letter, diagonals two
+        let split = string
+            .find(|c: char| c.is_ascii_digit())
+            .ok_or_else(|| anyhow::format_err!("No distance in {:?}", string))?;
+        let num = usize::from_str(&string[split..])?;
+        Ok(match &string[..split] {
             "U" => U(num),
             "D" => D(num),
             "L" => L(num),
             "R" => R(num),
+            "NE" => NE(num),
+            "NW" => NW(num),
+            "SE" => SE(num),
+            "SW" => SW(num),
             dir => anyhow::bail!("Unknown direction {}", dir),
         })
     }
 }
 
+/// What to minimize when picking the crossing to report.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Metric {
+    /// Manhattan distance from the origin.
+    Manhattan,
+    /// Combined steps along both wires.
+    Steps,
+}
+
+impl FromStr for Metric {
+    type Err = anyhow::Error;
+    fn from_str(string: &str) -> anyhow::Result<Metric> {
+        Ok(match string {
+            "manhattan" => Metric::Manhattan,
+            "steps" => Metric::Steps,
+            metric => anyhow::bail!("Unknown metric {}", metric),
+        })
+    }
+}
+
 /// Keeps track of which wires have visited a coordinate
 /// and how many steps it took each one to get there.
 struct WireStatus {
@@ -112,7 +164,17 @@ impl WireStatus {
     }
 }
 
+/// The manhattan distance of `coord` from the origin,
+/// or `None` if it's too far to represent.
+fn manhattan(coord: (isize, isize)) -> Option<usize> {
+    let dist = coord.0.checked_abs()?.checked_add(coord.1.checked_abs()?)?;
+    Some(dist as usize)
+}
+
 /// The wiring itself.
+///
+/// Ties are broken by the other metric, then by the smaller x, then the smaller y,
+/// so the same crossing is picked no matter which order they're found in.
 struct Wiring {
     /// Points that have been visited.
     wiring: HashMap<(isize, isize), WireStatus>,
@@ -120,8 +182,14 @@ struct Wiring {
     closest_crossing: (isize, isize),
     /// The distance to that crossing via manhattan distance.
     dist: usize,
+    /// The combined wire length at that crossing.
+    closest_length: usize,
     /// The current shortest length to a wire crossing.
     length: usize,
+    /// The crossing with that shortest length.
+    shortest_length_crossing: (isize, isize),
+    /// The manhattan distance to that crossing.
+    shortest_length_dist: usize,
 }
 
 impl Wiring {
@@ -130,7 +198,18 @@ impl Wiring {
             wiring: HashMap::new(),
             closest_crossing: (isize::max_value() / 2, isize::max_value() / 2),
             dist: usize::max_value(),
+            closest_length: usize::MAX,
             length: usize::max_value(),
+            shortest_length_crossing: (isize::MAX / 2, isize::MAX / 2),
+            shortest_length_dist: usize::MAX,
+        }
+    }
+
+    /// The best crossing by the given metric, and its value by that metric.
+    fn best(&self, metric: Metric) -> ((isize, isize), usize) {
+        match metric {
+            Metric::Manhattan => (self.closest_crossing, self.dist),
+            Metric::Steps => (self.shortest_length_crossing, self.length),
         }
     }
 
@@ -143,14 +222,28 @@ impl Wiring {
         wire_status.visit_from(wire_number, steps);
 
         if wire_status.is_crossed() {
-            let dist = (coord.0.abs() + coord.1.abs()) as usize;
-            if dist < self.dist {
-                self.closest_crossing = coord;
-                self.dist = dist;
-            }
             let length = wire_status.total_length();
-            if length < self.length {
+            // a crossing too far away to measure can't be the closest,
+            // but can still be the shortest
+            let dist = manhattan(coord);
+            if let Some(dist) = dist {
+                if (dist, length, coord) < (self.dist, self.closest_length, self.closest_crossing) {
+                    self.closest_crossing = coord;
+                    self.dist = dist;
+                    self.closest_length = length;
+                }
+            }
+            let dist = dist.unwrap_or(usize::MAX);
+            if (length, dist, coord)
+                < (
+                    self.length,
+                    self.shortest_length_dist,
+                    self.shortest_length_crossing,
+                )
+            {
                 self.length = length;
+                self.shortest_length_crossing = coord;
+                self.shortest_length_dist = dist;
             }
         }
     }
@@ -173,7 +266,22 @@ fn line_to_directions(line: &str) -> anyhow::Result<Vec<Direction>> {
     line.split(',').map(Direction::from_str).collect()
 }
 
+/// The metric given by `--metric <manhattan|steps>`, if any.
+fn parse_metric() -> anyhow::Result<Option<Metric>> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--metric" {
+            let metric = args
+                .next()
+                .ok_or_else(|| anyhow::format_err!("--metric needs manhattan or steps"))?;
+            return Ok(Some(metric.parse()?));
+        }
+    }
+    Ok(None)
+}
+
 fn main() -> anyhow::Result<()> {
+    let metric = parse_metric()?;
     let stdin = io::stdin();
     let mut lines = stdin.lock().lines();
     let one = lines.next().unwrap()?;
@@ -187,7 +295,16 @@ fn main() -> anyhow::Result<()> {
     wiring.run_wire(Wire::WIRE1, path1);
     wiring.run_wire(Wire::WIRE2, path2);
 
-    println!("Distance: {}\nLength: {}", wiring.dist, wiring.length);
+    if let Some(metric) = metric {
+        let (crossing, value) = wiring.best(metric);
+        println!("{} at {:?}", value, crossing);
+        return Ok(());
+    }
+
+    println!(
+        "Distance: {} at {:?}\nLength: {} at {:?}",
+        wiring.dist, wiring.closest_crossing, wiring.length, wiring.shortest_length_crossing
+    );
 
     Ok(())
 }
@@ -217,4 +334,45 @@ mod tests {
     test!(main_example ("R8,U5,L5,D3", "U7,R6,D4,L4") -> 6);
     test!(smol_1 ("R75,D30,R83,U83,L12,D49,R71,U7,L72", "U62,R66,U55,R34,D71,R55,D58,R83") -> 159, 610);
     test!(smol_2 ("R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51", "U98,R91,D20,R16,D67,R40,U7,R15,U6,R7") -> 135, 410);
+    test!(diagonal_across_straight ("NE4", "U2,R5") -> 4, 6);
+    test!(diagonal_across_diagonal ("NE4", "R4,NW4") -> 4, 8);
+
+    #[test]
+    fn crossing_coordinates() {
+        let mut wiring = Wiring::new();
+
+        wiring.run_wire(Wire::WIRE1, line_to_directions("R8,U5,L5,D3").unwrap());
+        wiring.run_wire(Wire::WIRE2, line_to_directions("U7,R6,D4,L4").unwrap());
+
+        assert_eq!(wiring.closest_crossing, (3, 3));
+        assert_eq!(wiring.length, 30);
+        assert_eq!(wiring.shortest_length_crossing, (6, 5));
+    }
+
+    #[test]
+    fn equidistant_crossings() {
+        let mut wiring = Wiring::new();
+
+        // Crosses at (-1, 1) and (1, 1), both 2 away,
+        // but the wires are 10 and 8 long at those points.
+        wiring.run_wire(Wire::WIRE1, line_to_directions("L2,U1,R4").unwrap());
+        wiring.run_wire(Wire::WIRE2, line_to_directions("R1,U2,L2,D1").unwrap());
+
+        assert_eq!(wiring.best(Metric::Manhattan), ((1, 1), 2));
+        assert_eq!(wiring.best(Metric::Steps), ((1, 1), 8));
+    }
+
+    #[test]
+    fn unmeasurable_crossings() {
+        let mut wiring = Wiring::new();
+
+        for &coord in &[(isize::MIN, 0), (isize::MIN + 1, -1)] {
+            wiring.set_wire(coord, Wire::WIRE1, &mut 1);
+            wiring.set_wire(coord, Wire::WIRE2, &mut 2);
+        }
+
+        assert_eq!(wiring.dist, usize::MAX);
+        assert_eq!(wiring.length, 3);
+        assert_eq!(wiring.shortest_length_crossing, (isize::MIN, 0));
+    }
 }
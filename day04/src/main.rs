@@ -1,21 +1,125 @@
+use anyhow::{format_err, Result};
 use std::io::{self, BufRead};
 use std::str::FromStr;
 
-fn stdin_range() -> (usize, usize) {
-    let stdin = io::stdin();
-    let mut reader = stdin.lock();
+/// Parse a range like `123456-654321`.
+fn parse_range(range: &str) -> Result<(usize, usize)> {
+    let mut split = range.trim().splitn(2, '-');
+    let lower = split.next().unwrap_or_default();
+    let higher = split
+        .next()
+        .ok_or_else(|| format_err!("Range {:?} has no '-'", range))?;
+
+    Ok((usize::from_str(lower)?, usize::from_str(higher)?))
+}
+
+/// Parse the range from the first line of `reader`.
+fn read_range(mut reader: impl BufRead) -> Result<(usize, usize)> {
     let mut line = String::new();
-    reader.read_line(&mut line);
+    reader.read_line(&mut line)?;
+    parse_range(&line)
+}
+
+fn digits(n: usize) -> Vec<u8> {
+    n.to_string().into_bytes()
+}
 
-    let mut split = line.split('-');
-    let lower = usize::from_str(split.next().unwrap()).unwrap();
-    let higher = usize::from_str(split.next().unwrap()).unwrap();
+fn is_non_decreasing(digits: &[u8]) -> bool {
+    digits.windows(2).all(|pair| pair[0] <= pair[1])
+}
+
+/// The length of each run of identical adjacent digits.
+fn run_lengths(digits: &[u8]) -> Vec<usize> {
+    let mut runs = Vec::new();
+    let mut iter = digits.iter().peekable();
+
+    while let Some(digit) = iter.next() {
+        let mut length = 1;
+        while iter.peek() == Some(&digit) {
+            iter.next();
+            length += 1;
+        }
+        runs.push(length);
+    }
+
+    runs
+}
 
-    (lower, higher)
+/// Six digits, never decreasing, with at least one adjacent pair.
+fn is_valid_part1(n: usize) -> bool {
+    let digits = digits(n);
+    digits.len() == 6
+        && is_non_decreasing(&digits)
+        && run_lengths(&digits).into_iter().any(|length| length >= 2)
 }
 
+/// Like part 1, but at least one pair must not be part of a larger group.
+fn is_valid_part2(n: usize) -> bool {
+    is_valid_part1(n)
+        && run_lengths(&digits(n))
+            .into_iter()
+            .any(|length| length == 2)
+}
+
+/// Takes the range as an argument, or from stdin if there isn't one.
+/// `--count-only` prints just the two counts.
+fn main() -> Result<()> {
+    let (flags, ranges): (Vec<String>, Vec<String>) = std::env::args()
+        .skip(1)
+        .partition(|arg| arg.starts_with("--"));
+    let count_only = flags.iter().any(|flag| flag == "--count-only");
+
+    let (lower, higher) = match ranges.first() {
+        Some(range) => parse_range(range)?,
+        None => read_range(io::stdin().lock())?,
+    };
+
+    let part1 = (lower..=higher).filter(|&n| is_valid_part1(n)).count();
+    let part2 = (lower..=higher).filter(|&n| is_valid_part2(n)).count();
+
+    if count_only {
+        println!("{}\n{}", part1, part2);
+    } else {
+        println!("Part 1: {}\nPart 2: {}", part1, part2);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! test {
+        ($name:ident ($n:expr) -> $part1:expr, $part2:expr) => {
+            #[test]
+            fn $name() {
+                assert_eq!(is_valid_part1($n), $part1);
+                assert_eq!(is_valid_part2($n), $part2);
+            }
+        };
+    }
+
+    test!(all_ones (111111) -> true, false);
+    test!(decreasing (223450) -> false, false);
+    test!(no_double (123789) -> false, false);
+    test!(all_pairs (112233) -> true, true);
+    test!(triple_only (123444) -> true, false);
+    test!(quad_and_pair (111122) -> true, true);
+    test!(too_short (11234) -> false, false);
+
+    #[test]
+    fn range_from_arg() {
+        assert_eq!(parse_range("123456-654321").unwrap(), (123456, 654321));
+        assert!(parse_range("123456").is_err());
+        assert!(parse_range("abc-654321").is_err());
+    }
 
+    #[test]
+    fn range_from_reader() {
+        use std::io::Cursor;
 
-fn main() {
-    println!("Hello, world!");
+        let reader = Cursor::new("123456-654321\nignored\n");
+        assert_eq!(read_range(reader).unwrap(), (123456, 654321));
+        assert!(read_range(Cursor::new("")).is_err());
+    }
 }
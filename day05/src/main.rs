@@ -1,15 +1,39 @@
-use anyhow::{self, Result};
+use anyhow::{self, format_err, Result};
 use intcode::{stdin_to_prog, IntcodeComputer};
 
+/// The diagnostic code is the last output. Every output before it is a self-test,
+/// which should be 0 if the computer is working, so warn about any that aren't.
+fn diagnostic_code(outputs: &[isize]) -> Result<isize> {
+    let (&code, tests) = outputs
+        .split_last()
+        .ok_or_else(|| format_err!("Program gave no output"))?;
+
+    for (idx, &test) in tests.iter().enumerate() {
+        if test != 0 {
+            eprintln!("warning: self-test {} failed with {}", idx, test);
+        }
+    }
+
+    Ok(code)
+}
+
 fn main() -> Result<()> {
     let prog = stdin_to_prog()?;
+    let system_id = if cfg!(feature = "part2") { 5 } else { 1 };
 
-    let mut cpu = IntcodeComputer::new_with_io(
-        prog,
-        || if cfg!(feature = "part2") { 5 } else { 1 },
-        |i| println!("{}", i),
-    );
-
-    cpu.execute()?;
+    let outputs = IntcodeComputer::new(prog).run_collect(Some(system_id))?;
+    println!("{}", diagnostic_code(&outputs)?);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::diagnostic_code;
+
+    #[test]
+    fn last_output_is_code() {
+        assert_eq!(diagnostic_code(&[0, 0, 0, 42]).unwrap(), 42);
+        assert_eq!(diagnostic_code(&[0, 3, 42]).unwrap(), 42);
+        assert!(diagnostic_code(&[]).is_err());
+    }
+}
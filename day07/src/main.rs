@@ -1,95 +1,155 @@
-use anyhow::{self, format_err, Result};
+use anyhow::{self, bail, Result};
 use intcode::*;
-use std::collections::HashSet;
+use std::io::Write;
 use Event::*;
 
-fn compute_chain(phases: impl IntoIterator<Item = isize>, program: &Vec<isize>) -> Result<isize> {
+/// Run each amplifier once, in order, passing the signal along.
+/// Each amplifier's phase and the signal it sent are written to `log`.
+fn compute_chain(
+    phases: impl IntoIterator<Item = isize>,
+    program: &[isize],
+    log: &mut impl Write,
+) -> Result<isize> {
     let mut signal = 0;
     for phase_setting in phases {
-        let mut cpu = IntcodeComputer::new(program.clone());
-        dbg!(cpu.execute(Some(phase_setting))?);
-        if let HaveOutput(x) = dbg!(cpu.execute(Some(signal))?) {
+        let mut cpu = IntcodeComputer::from(program);
+        if let HaveOutput(x) =
+            cpu.execute_from_iter(&mut vec![phase_setting, signal].into_iter())?
+        {
             signal = x;
         }
-        dbg!(cpu.execute(None)?);
+        writeln!(log, "phase {}: signal {}", phase_setting, signal)?;
     }
 
     Ok(signal)
 }
 
-struct FeedbackLoopAmp {
-    cpu: IntcodeComputer,
+fn feedback_loop(phases: impl IntoIterator<Item = isize>, program: &[isize]) -> Result<isize> {
+    let phases: Vec<isize> = phases.into_iter().collect();
+    AmplifierChain::new(program, &phases).run(0)
 }
 
-impl FeedbackLoopAmp {
-    fn new(phase_signal: isize, mut cpu: IntcodeComputer) -> Result<FeedbackLoopAmp> {
-        if let RequestingInput = cpu.execute(Some(phase_signal))? {
-            Ok(FeedbackLoopAmp { cpu })
-        } else {
-            Err(format_err!("Bad event"))
-        }
-    }
-    fn call(&mut self, signal: isize) -> Result<Option<isize>> {
-        match self.cpu.execute(Some(signal))? {
-            HaveOutput(x) => Ok(Some(x)),
-            Halted => Ok(None),
-            _ => Err(format_err!("Bad event")),
-        }
-    }
+/// The amplifiers' final signal for one phase sequence,
+/// and for part 1, the log of what each amplifier sent.
+fn evaluate(sequence: &[isize], program: &[isize]) -> Result<(isize, String)> {
+    let mut log = Vec::new();
+    let signal = if cfg!(feature = "part2") {
+        feedback_loop(sequence.iter().copied(), program)?
+    } else {
+        compute_chain(sequence.iter().copied(), program, &mut log)?
+    };
+    Ok((signal, String::from_utf8(log)?))
 }
 
-fn feedback_loop(phases: impl IntoIterator<Item = isize>, program: &Vec<isize>) -> Result<isize> {
-    let mut signal = 0;
-    let mut idx = 0;
+/// Each sequence's output and log, in order, run across threads.
+#[cfg(feature = "parallel")]
+fn evaluate_all(sequences: &[Vec<isize>], program: &[isize]) -> Result<Vec<(isize, String)>> {
+    use rayon::prelude::*;
+    sequences
+        .par_iter()
+        .map(|sequence| evaluate(sequence, program))
+        .collect()
+}
 
-    let mut cpus = phases
+#[cfg(not(feature = "parallel"))]
+fn evaluate_all(sequences: &[Vec<isize>], program: &[isize]) -> Result<Vec<(isize, String)>> {
+    sequences
+        .iter()
+        .map(|sequence| evaluate(sequence, program))
+        .collect()
+}
+
+/// The program's path, defaulting to `input`, and whether `--verbose` was given.
+fn parse_args() -> (String, bool) {
+    let (flags, paths): (Vec<String>, Vec<String>) = std::env::args()
+        .skip(1)
+        .partition(|arg| arg.starts_with("--"));
+    let path = paths
         .into_iter()
-        .map(|setting| FeedbackLoopAmp::new(setting, IntcodeComputer::new(program.clone())))
-        .collect::<Result<Vec<FeedbackLoopAmp>>>()?;
-
-    loop {
-        if let Some(new_signal) = cpus[idx].call(signal)? {
-            signal = new_signal;
-            idx = (idx + 1) % cpus.len();
-        } else {
-            break Ok(signal);
-        }
-    }
+        .next()
+        .unwrap_or_else(|| "input".to_owned());
+    (path, flags.iter().any(|flag| flag == "--verbose"))
 }
 
+/// With `--verbose`, part 1 also prints each amplifier's phase and signal to stderr,
+/// a sequence at a time, each line labelled with its sequence.
 fn main() -> Result<()> {
-    let program = first_arg_to_prog()?;
-    let mut max_output = 0;
-
-    let phase_range = if cfg!(feature = "part2") { 5..=9 } else { 0..=4 };
-
-    'p1: for phase1 in phase_range.clone() {
-        'p2: for phase2 in phase_range.clone() {
-            'p3: for phase3 in phase_range.clone() {
-                'p4: for phase4 in phase_range.clone() {
-                    'p5: for phase5 in phase_range.clone() {
-                        let sequence = [phase1, phase2, phase3, phase4, phase5];
-                        let mut hash = HashSet::new();
-                        for i in sequence.iter() {
-                            if !hash.insert(*i) {
-                                continue 'p5;
-                            }
-                        }
-                        let computed_putput = if cfg!(feature="part2") {
-                            feedback_loop(sequence.iter().map(|x| *x), &program)?
-                        } else {
-                            compute_chain(sequence.iter().map(|x| *x), &program)?
-                        };
-                        println!("{:?} = {}", sequence, computed_putput);
-                        if computed_putput > max_output {
-                            max_output = computed_putput;
-                        }
-                    }
-                }
+    let (path, verbose) = parse_args();
+    if verbose && cfg!(feature = "part2") {
+        bail!("--verbose only traces part 1's chain of amplifiers");
+    }
+    let program = from_file(path)?;
+
+    let phases = if cfg!(feature = "part2") {
+        [5, 6, 7, 8, 9]
+    } else {
+        [0, 1, 2, 3, 4]
+    };
+
+    let sequences: Vec<Vec<isize>> = Permutations::new(&phases).collect();
+    let results = evaluate_all(&sequences, &program)?;
+    for (sequence, (output, log)) in sequences.iter().zip(&results) {
+        println!("{:?} = {}", sequence, output);
+        if verbose {
+            for line in log.lines() {
+                eprintln!("{:?} {}", sequence, line);
             }
         }
     }
 
+    let max_output = results
+        .into_iter()
+        .map(|(output, _)| output)
+        .max()
+        .unwrap_or(0);
     println!("{}", max_output);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn permutations_are_distinct() {
+        let perms: Vec<_> = Permutations::new(&[0, 1, 2, 3, 4]).collect();
+
+        assert_eq!(perms.len(), 120);
+        assert_eq!(perms.iter().collect::<HashSet<_>>().len(), 120);
+    }
+
+    #[test]
+    fn chain_example() {
+        let program = vec![
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+
+        let mut log = Vec::new();
+        assert_eq!(
+            compute_chain(vec![4, 3, 2, 1, 0], &program, &mut log).unwrap(),
+            43210
+        );
+        assert_eq!(
+            String::from_utf8(log).unwrap(),
+            "phase 4: signal 4\n\
+             phase 3: signal 43\n\
+             phase 2: signal 432\n\
+             phase 1: signal 4321\n\
+             phase 0: signal 43210\n"
+        );
+    }
+
+    #[test]
+    fn feedback_example() {
+        let program = vec![
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+
+        assert_eq!(
+            feedback_loop(vec![9, 8, 7, 6, 5], &program).unwrap(),
+            139629729
+        );
+    }
+}
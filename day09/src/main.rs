@@ -1,19 +1,29 @@
-use anyhow::{self, bail, Result};
+use anyhow::{self, format_err, Result};
 use intcode::*;
-use Event::*;
+use std::str::FromStr;
+
+/// The BOOST program's mode: 1 for test mode, 2 for sensor boost.
+/// Taken from the first argument, defaulting to 1.
+fn mode_arg() -> Result<isize> {
+    match std::env::args().nth(1) {
+        Some(arg) => {
+            isize::from_str(&arg).map_err(|_| format_err!("Mode must be an integer, got {:?}", arg))
+        }
+        None => Ok(1),
+    }
+}
 
 fn main() -> Result<()> {
-    let prog = first_arg_to_prog()?;
+    let mode = mode_arg()?;
+    // the first argument is the mode, so the program comes second
+    let prog = from_file(
+        std::env::args()
+            .nth(2)
+            .unwrap_or_else(|| "input".to_owned()),
+    )?;
 
-    let mut cpu = IntcodeComputer::new(prog);
-    let mut input = Some(2);
-    loop {
-        match cpu.execute(input.take())? {
-            HaveOutput(x) => {
-                println!("{}", x);
-            }
-            Halted => break Ok(()),
-            _ => bail!("Requesting input again"),
-        }
+    for output in IntcodeComputer::new(prog).run_collect(Some(mode))? {
+        println!("{}", output);
     }
+    Ok(())
 }
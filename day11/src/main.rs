@@ -2,9 +2,9 @@ use anyhow::{self, bail, format_err, Error, Result};
 use intcode::*;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
-use std::cmp::{max, min};
-use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
+use std::fs::File;
+use std::io::{BufWriter, Write};
 use Event::*;
 
 #[derive(FromPrimitive)]
@@ -58,8 +58,9 @@ impl Direction {
     }
 }
 
-#[derive(FromPrimitive, Clone, Copy)]
+#[derive(FromPrimitive, Clone, Copy, Default)]
 enum PanelColor {
+    #[default]
     Black = 0,
     White = 1,
 }
@@ -84,62 +85,169 @@ impl Robot {
     }
 }
 
-fn main() -> Result<()> {
-    let prog = first_arg_to_prog()?;
-    let mut cpu = IntcodeComputer::new(prog);
+/// The program's path, defaulting to `input`,
+/// and where to write the hull as an image if `--image <path>` was given.
+fn parse_args() -> Result<(String, Option<String>)> {
+    let mut program = None;
+    let mut image = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--image" {
+            image = Some(
+                args.next()
+                    .ok_or_else(|| format_err!("--image needs a path"))?,
+            );
+        } else {
+            program = Some(arg);
+        }
+    }
+    Ok((program.unwrap_or_else(|| "input".to_owned()), image))
+}
+
+/// The hull from the top row down, trimmed to the panels the robot visited,
+/// with `true` for white panels.
+fn hull_grid(ship: &Grid<PanelColor>) -> Vec<Vec<bool>> {
+    ship.rows()
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|color| matches!(color, PanelColor::White))
+                .collect()
+        })
+        .collect()
+}
 
-    let mut ship = HashMap::new();
-    ship.insert((0, 0), PanelColor::White);
+/// Write the hull as a plain portable bitmap, with white panels as set pixels.
+fn write_pbm(out: &mut impl Write, rows: &[Vec<bool>]) -> Result<()> {
+    writeln!(out, "P1")?;
+    writeln!(out, "{} {}", rows.first().map_or(0, Vec::len), rows.len())?;
+    for row in rows {
+        let pixels: Vec<&str> = row
+            .iter()
+            .map(|&white| if white { "1" } else { "0" })
+            .collect();
+        writeln!(out, "{}", pixels.join(" "))?;
+    }
+    Ok(())
+}
+
+/// Run the robot from a `start` panel until it halts, giving every panel it painted.
+/// Panels it only passed over, or stopped on, aren't included.
+fn paint(cpu: &mut IntcodeComputer, start: PanelColor) -> Result<Grid<PanelColor>> {
+    let mut ship = Grid::y_up();
     let mut robot = Robot {
         coords: (0, 0),
         direction: Direction::N,
     };
 
-    let mut min_x = isize::max_value();
-    let mut min_y = isize::max_value();
-    let mut max_x = isize::min_value();
-    let mut max_y = isize::min_value();
-
     loop {
-        let panel_color = ship.entry(robot.coords).or_insert(PanelColor::Black);
-        let color = match cpu.execute(Some(*panel_color as isize))? {
+        let panel_color = match ship.get(robot.coords) {
+            Some(&color) => color,
+            None if robot.coords == (0, 0) => start,
+            None => PanelColor::Black,
+        };
+        let input = panel_color as isize;
+        let color = match cpu.execute(&mut || Some(input))? {
             HaveOutput(x) => x.try_into()?,
             Halted => break,
             _ => bail!("Unexpected color"),
         };
 
-        *panel_color = color;
+        ship.insert(robot.coords, color);
 
-        let rotation = if let HaveOutput(x) = cpu.execute(Some(*panel_color as isize))? {
+        let rotation = if let HaveOutput(x) = cpu.execute(&mut || Some(input))? {
             x.try_into()?
         } else {
             bail!("Unexpected rotation")
         };
 
         robot.rotate_and_move(rotation);
-
-        min_x = min(min_x, robot.coords.0);
-        max_x = max(max_x, robot.coords.0);
-        min_y = min(min_y, robot.coords.1);
-        max_y = max(max_y, robot.coords.1);
     }
+    Ok(ship)
+}
 
-    println!("{}", ship.len());
-    println!("min ({}, {}) max ({}, {})", min_x, min_y, max_x, max_y);
+fn main() -> Result<()> {
+    let (prog, image) = parse_args()?;
+    let prog = from_file(prog)?;
+    let mut cpu = IntcodeComputer::new(prog);
 
-    let height = max_y - min_y;
-    let width = max_x - min_x;
-    let mut screen = vec![".".repeat(width as usize + 1).into_bytes(); height as usize + 1];
+    // Part 1 starts on a black panel and only wants to know how many panels get painted.
+    // Part 2 starts on a white panel, and the painted hull spells out the registration.
+    let start = if cfg!(feature = "part2") {
+        PanelColor::White
+    } else {
+        PanelColor::Black
+    };
+    let ship = paint(&mut cpu, start)?;
 
-    for (coord, color) in ship {
-        screen[(coord.1 - min_y) as usize][(coord.0 - min_x) as usize] = match color {
-            PanelColor::Black => b'.',
-            PanelColor::White => b'#',
-        };
+    if !cfg!(feature = "part2") {
+        println!("{}", ship.len());
+        return Ok(());
+    }
+
+    if let Some(((min_x, min_y), (max_x, max_y))) = ship.bounds() {
+        println!("min ({}, {}) max ({}, {})", min_x, min_y, max_x, max_y);
+    }
+
+    let hull = hull_grid(&ship);
+    if let Some(path) = image {
+        let mut file = BufWriter::new(File::create(path)?);
+        return write_pbm(&mut file, &hull);
     }
 
-    for bytes in screen.into_iter().rev() {
-        println!("{}", String::from_utf8(bytes)?);
+    for row in hull {
+        let line: String = row
+            .into_iter()
+            .map(|white| if white { '#' } else { '.' })
+            .collect();
+        println!("{}", line);
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pbm() {
+        let mut out = Vec::new();
+        write_pbm(&mut out, &[vec![true, false], vec![false, true]]).unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), "P1\n2 2\n1 0\n0 1\n");
+    }
+
+    #[test]
+    fn paints_an_l() {
+        // (color, turn) pairs: white right, black right, black right,
+        // then white left three times
+        let mut cpu = IntcodeComputer::new(vec![
+            104, 1, 104, 1, 104, 0, 104, 1, 104, 0, 104, 1, 104, 1, 104, 0, 104, 1, 104, 0, 104, 1,
+            104, 0, 99,
+        ]);
+        let ship = paint(&mut cpu, PanelColor::Black).unwrap();
+
+        assert_eq!(
+            hull_grid(&ship),
+            vec![vec![true, false], vec![true, false], vec![true, true]]
+        );
+    }
+
+    #[test]
+    fn counts_painted_panels() {
+        // paint white and turn right, then stop on the next panel without painting it
+        let mut cpu = IntcodeComputer::new(vec![104, 1, 104, 1, 99]);
+        let ship = paint(&mut cpu, PanelColor::Black).unwrap();
+
+        assert_eq!(ship.len(), 1);
+    }
+
+    #[test]
+    fn reads_start_color() {
+        // paint the opposite of whatever the robot's on, then stop
+        let mut cpu = IntcodeComputer::new(vec![3, 11, 1008, 11, 0, 11, 4, 11, 104, 0, 99, 0]);
+        let ship = paint(&mut cpu, PanelColor::White).unwrap();
+
+        assert_eq!(hull_grid(&ship), vec![vec![false]]);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{hack_quarters, Tile};
+use anyhow::{bail, Result};
+use cursive::{
+    direction::Direction,
+    event::{self, EventResult},
+    view::View,
+    Cursive, Printer, Vec2,
+};
+use intcode::{Event, Grid, IntcodeComputer};
+use std::convert::TryFrom;
+
+enum JoystickMovement {
+    Left = -1,
+    Neutral = 0,
+    Right = 1,
+}
+
+impl TryFrom<event::Event> for JoystickMovement {
+    type Error = ();
+    fn try_from(event: event::Event) -> Result<Self, Self::Error> {
+        use event::Event::*;
+        use event::Key::*;
+        match event {
+            Key(Right) => Ok(JoystickMovement::Right),
+            Key(Left) => Ok(JoystickMovement::Left),
+            Char(' ') => Ok(JoystickMovement::Neutral),
+            _ => Err(()),
+        }
+    }
+}
+
+struct Game {
+    cpu: IntcodeComputer,
+    tiles: Grid<Tile>,
+    score: isize,
+    /// Shown under the score once the game ends or breaks.
+    status: Option<String>,
+}
+
+impl Game {
+    fn new(mut cpu: IntcodeComputer) -> Result<Game> {
+        hack_quarters(&mut cpu);
+        let mut game = Game {
+            cpu,
+            tiles: Grid::new(),
+            score: 0,
+            status: None,
+        };
+        game.run(None)?;
+        Ok(game)
+    }
+
+    /// Run until the game wants the joystick moved again,
+    /// drawing tiles and updating the score as they're output.
+    fn run(&mut self, mut joystick: Option<isize>) -> Result<()> {
+        use Event::*;
+        loop {
+            let x = match self.cpu.execute(&mut || joystick.take())? {
+                HaveOutput(x) => x,
+                RequestingInput => break Ok(()),
+                Halted => {
+                    self.status = Some("Game over! Press q to quit.".to_owned());
+                    break Ok(());
+                }
+                Breakpoint(_) | SelfModified { .. } => continue,
+            };
+
+            match (
+                self.cpu.execute(&mut || None)?,
+                self.cpu.execute(&mut || None)?,
+            ) {
+                (HaveOutput(y), HaveOutput(tile)) => {
+                    if x == -1 && y == 0 {
+                        self.score = tile;
+                    } else {
+                        self.tiles.insert((x, y), Tile::try_from(tile)?);
+                    }
+                }
+                _ => bail!("Unexpected output"),
+            }
+        }
+    }
+}
+
+impl View for Game {
+    fn draw(&self, printer: &Printer) {
+        printer.print((0, 0), &format!("Score: {}", self.score));
+        if let Some(status) = &self.status {
+            printer.print((0, 1), status);
+        }
+
+        for (&(x, y), &tile) in self.tiles.iter() {
+            printer.print((x as usize, y as usize + 2), &char::from(&tile).to_string());
+        }
+    }
+
+    fn required_size(&mut self, _constraint: Vec2) -> Vec2 {
+        let (width, height) = match self.tiles.bounds() {
+            Some((_, (max_x, max_y))) => (max_x as usize + 1, max_y as usize + 1),
+            None => (0, 0),
+        };
+        Vec2::new(width.max(30), height + 2)
+    }
+
+    fn take_focus(&mut self, _source: Direction) -> bool {
+        true
+    }
+
+    fn on_event(&mut self, event: event::Event) -> EventResult {
+        let movement = match JoystickMovement::try_from(event) {
+            Ok(movement) if self.status.is_none() => movement,
+            _ => return EventResult::Ignored,
+        };
+
+        if let Err(e) = self.run(Some(movement as isize)) {
+            self.status = Some(format!("Error: {}. Press q to quit.", e));
+        }
+        EventResult::Consumed(None)
+    }
+}
+
+/// Play part 2 by hand: left and right move the joystick, space holds it still,
+/// and the game only advances when a key is pressed.
+pub fn play(cpu: IntcodeComputer) -> Result<()> {
+    let game = Game::new(cpu)?;
+
+    let mut siv = Cursive::default();
+    siv.add_layer(game);
+    siv.add_global_callback('q', |s| s.quit());
+    siv.run();
+    Ok(())
+}
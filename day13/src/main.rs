@@ -2,18 +2,17 @@ use anyhow::{self, bail, format_err, Error, Result};
 use intcode::*;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
-// use cursive::{
-//     self,
-//     event::{self, EventResult},
-//     view::View,
-//     Cursive, Printer, Vec2,
-// };
 use std::collections::HashMap;
-use std::collections::VecDeque;
-use std::convert::{TryFrom, TryInto};
+use std::convert::TryFrom;
+use std::thread;
+use std::time::Duration;
 
-#[derive(FromPrimitive, PartialEq, Debug)]
+#[cfg(feature = "interactive")]
+mod interactive;
+
+#[derive(FromPrimitive, PartialEq, Debug, Clone, Copy, Default)]
 enum Tile {
+    #[default]
     Empty = 0,
     Wall = 1,
     Block = 2,
@@ -29,248 +28,382 @@ impl TryFrom<isize> for Tile {
     }
 }
 
-// impl From<Tile> for &'static str {
-//     fn from(tile: Tile) -> &'static str {
-//         use Tile::*;
-//         match tile {
-//             Empty => " ",
-//             Wall => "|",
-//             Block => "#",
-//             Paddle => "=",
-//             Ball => "o",
-//         }
-//     }
-// }
-
-// enum JoystickMovement {
-//     Left = -1,
-//     Neutral = 0,
-//     Right = 1,
-// }
-
-// impl TryFrom<event::Event> for JoystickMovement {
-//     type Error = ();
-//     fn try_from(event: event::Event) -> Result<Self, Self::Error> {
-//         use event::Event::*;
-//         use event::Key::*;
-//         match event {
-//             Key(Right) => Ok(JoystickMovement::Right),
-//             Key(Left) => Ok(JoystickMovement::Left),
-//             Char(' ') => Ok(JoystickMovement::Neutral),
-//             _ => Err(()),
-//         }
-//     }
-// }
-
-// struct Draw(isize, isize, Tile);
-
-// enum GameEvent {
-//     Draw(Draw),
-//     GameOver,
-//     RequestingInput,
-// }
-
-// struct Game {
-//     cpu: IntcodeComputer,
-//     score: isize,
-//     // input_buffer: Option<JoystickMovement>,
-//     draw_buffer: VecDeque<Draw>,
-// }
-
-// impl Game {
-//     fn new(mut cpu: IntcodeComputer) -> Result<Game> {
-//         let mut game = Game {
-//             cpu,
-//             score: 0,
-//             // input_buffer: None,
-//             draw_buffer: VecDeque::new(),
-//         };
-
-//         loop {
-//             match game.input_execute(None)? {
-//                 GameEvent::Draw(draw) => game.draw_buffer.push_back(draw),
-//                 GameEvent::GameOver => bail!("unexpected game over"),
-//                 GameEvent::RequestingInput => break Ok(game),
-//             }
-//         }
-//     }
-
-//     fn input_execute(&mut self, input: Option<isize>) -> Result<GameEvent> {
-//         loop {
-//             match self.execute(input)? {
-//                 GameEvent::Draw(draw) => self.draw_buffer.push_back(draw),
-//                 x => break Ok(x),
-//             }
-//         }
-//     }
-
-//     fn draw(&mut self, printer: &Printer) {
-//         while let Some(Draw(x, y, tile)) = self.draw_buffer.pop_front() {
-//             let x = x.try_into().unwrap();
-//             let y = y.try_into().unwrap();
-
-//             let coords = Vec2 { x, y };
-//             let tile = tile.into();
-//             printer.print(coords, tile);
-//         }
-//     }
-
-//     fn execute(&mut self, input: Option<isize>) -> Result<GameEvent> {
-//         use Event::*;
-//         loop {
-//             let x = match self.cpu.execute(input)? {
-//                 HaveOutput(x) => x,
-//                 RequestingInput => return Ok(GameEvent::RequestingInput),
-//                 Halted => return Ok(GameEvent::GameOver),
-//             };
-
-//             match (self.cpu.execute(None)?, self.cpu.execute(None)?) {
-//                 (HaveOutput(y), HaveOutput(tile)) => {
-//                     if x == -1 && y == 0 {
-//                         self.score = tile;
-//                     } else {
-//                         return Ok(GameEvent::Draw(Draw(x, y, Tile::try_from(tile)?)));
-//                     }
-//                 }
-//                 _ => bail!("Unexpected output"),
-//             }
-//         }
-//     }
-// }
-
-// impl View for Game {
-//     fn draw(&self, printer: &Printer) {}
-
-//     fn on_event(&mut self, event: event::Event) -> EventResult {
-//         let input = if let Ok(movement) = JoystickMovement::try_from(event) {
-//             movement
-//         } else {
-//             return EventResult::Ignored;
-//         };
-
-//         let _evt = self.input_execute(Some(input as isize)).unwrap();
-
-//         EventResult::Consumed(None)
-//     }
-// }
-
-fn hack_quarters(prog: &mut Vec<isize>) {
-    prog[0] = 2;
+/// How a tile is drawn, in plain ASCII with the `ascii` feature.
+impl From<&Tile> for char {
+    fn from(tile: &Tile) -> char {
+        use Tile::*;
+        if cfg!(feature = "ascii") {
+            match tile {
+                Empty => ' ',
+                Wall => '|',
+                Block => '#',
+                Paddle => '=',
+                Ball => 'o',
+            }
+        } else {
+            match tile {
+                Empty => ' ',
+                Wall => '█',
+                Block => '▒',
+                Paddle => '▬',
+                Ball => '●',
+            }
+        }
+    }
 }
 
-fn none() -> Option<isize> {
-    None
+fn hack_quarters(cpu: &mut IntcodeComputer) {
+    cpu.poke(0, 2);
 }
 
-fn part_1(mut cpu: IntcodeComputer) -> Result<()> {
-    use intcode::Event::*;
+/// Run the game without quarters and count the blocks left on the screen.
+fn count_blocks(mut cpu: IntcodeComputer) -> Result<usize> {
     let mut screen = HashMap::new();
-    let mut score = 0;
 
-    loop {
-        let x = match cpu.execute(&mut none)? {
-            HaveOutput(x) => x,
-            Halted => break,
-            _ => bail!("unexpected output"),
-        };
-
-        match (cpu.execute(&mut none)?, cpu.execute(&mut none)?) {
-            (HaveOutput(y), HaveOutput(tile)) => {
-                if x == -1 && y == 0 {
-                    score = tile;
-                } else {
-                    screen.insert((x, y), Tile::try_from(tile)?);
-                }
-            }
-            _ => bail!("Unexpected output"),
+    while let Some(&[x, y, tile]) = cpu.next_n_outputs(3, &mut no_input)?.as_deref() {
+        // the score isn't a tile
+        if x == -1 && y == 0 {
+            continue;
         }
+        screen.insert((x, y), Tile::try_from(tile)?);
     }
 
-    let block_count = screen
-        .into_iter()
-        .filter(|(_, tile)| *tile == Tile::Block)
-        .count();
-    println!("{}", block_count);
+    Ok(screen.values().filter(|&&tile| tile == Tile::Block).count())
+}
+
+fn part_1(cpu: IntcodeComputer) -> Result<()> {
+    println!("{}", count_blocks(cpu)?);
     Ok(())
 }
 
-struct Game(IntcodeComputer);
+struct Game {
+    cpu: IntcodeComputer,
+    /// Every tile drawn so far, for rendering.
+    tiles: Grid<Tile>,
+    /// Where the ball was last drawn, for working out its velocity.
+    last_ball: Option<(isize, isize)>,
+}
 
 enum GameEvent {
     UpdateScore(isize),
-    BallPos(isize),
-    PaddlePos(isize),
+    /// Where the ball is now, and how far it's moved since it was last drawn.
+    BallPos {
+        pos: (isize, isize),
+        velocity: (isize, isize),
+    },
+    PaddlePos((isize, isize)),
     Halted
 }
 
 impl Game {
+    fn new(cpu: IntcodeComputer) -> Game {
+        Game {
+            cpu,
+            tiles: Grid::new(),
+            last_ball: None,
+        }
+    }
+
     fn execute(&mut self, input: &mut dyn FnMut() -> Option<isize>) -> Result<GameEvent> {
-        use Event::*;
         loop {
-            let x = match self.0.execute(input)? {
-                HaveOutput(x) => x,
-                Halted => break Ok(GameEvent::Halted),
-                _ => bail!("unexpected output"),
+            let (x, y, tile) = match self.cpu.next_n_outputs(3, input)?.as_deref() {
+                Some(&[x, y, tile]) => (x, y, tile),
+                _ => break Ok(GameEvent::Halted),
             };
-    
-            match (self.0.execute(input)?, self.0.execute(input)?) {
-                (HaveOutput(y), HaveOutput(tile)) => {
-                    if x == -1 && y == 0 {
-                        break Ok(GameEvent::UpdateScore(tile))
-                    } else {
-                        match Tile::try_from(tile)? {
-                            Tile::Ball => break Ok(GameEvent::BallPos(x)),
-                            Tile::Paddle => break Ok(GameEvent::PaddlePos(x)),
-                            _ => continue,
-                        }
-                    }
+
+            if x == -1 && y == 0 {
+                break Ok(GameEvent::UpdateScore(tile));
+            }
+            let tile = Tile::try_from(tile)?;
+            self.tiles.insert((x, y), tile);
+            match tile {
+                Tile::Ball => {
+                    let velocity = self
+                        .last_ball
+                        .map_or((0, 0), |(last_x, last_y)| (x - last_x, y - last_y));
+                    self.last_ball = Some((x, y));
+                    break Ok(GameEvent::BallPos {
+                        pos: (x, y),
+                        velocity,
+                    });
                 }
-                _ => bail!("Unexpected output"),
+                Tile::Paddle => break Ok(GameEvent::PaddlePos((x, y))),
+                _ => continue,
             }
         }
     }
+
+    /// The columns the ball can move through, between the side walls.
+    fn inner_columns(&self) -> Option<(isize, isize)> {
+        self.tiles
+            .bounds()
+            .map(|((min_x, _), (max_x, _))| (min_x + 1, max_x - 1))
+    }
+
+    /// Clear the terminal and draw every tile seen so far.
+    fn render(&self) {
+        let frame = self.tiles.render(|tile| tile.into());
+        // clear the screen and move the cursor to the top left
+        print!("\x1b[2J\x1b[H{}", frame);
+    }
+}
+
+/// How the AI picks where to move the paddle.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+enum Strategy {
+    /// Stay under the ball. Simple, but the paddle jitters back and forth.
+    #[default]
+    Follow,
+    /// Move to where the ball will come down, going by its velocity.
+    Predict,
+}
+
+/// Where a ball at `pos` moving by `velocity` will be when it reaches the row above `paddle_y`,
+/// bouncing between `columns`. Blocks it hits on the way aren't accounted for,
+/// so this is only a guess until the ball gets close.
+/// If the ball isn't on its way down, it's where the ball is now.
+fn landing_x(
+    pos: (isize, isize),
+    velocity: (isize, isize),
+    paddle_y: isize,
+    (left, right): (isize, isize),
+) -> isize {
+    let (x, y) = pos;
+    let (dx, dy) = velocity;
+    if dy <= 0 || right <= left {
+        return x;
+    }
+
+    let unbounced = x + dx * (paddle_y - 1 - y) / dy;
+    // the ball goes there and back between the walls
+    let width = right - left;
+    let offset = (unbounced - left).rem_euclid(2 * width);
+    if offset <= width {
+        left + offset
+    } else {
+        left + 2 * width - offset
+    }
+}
+
+/// Which way to tilt the joystick to move the paddle toward `target`.
+fn joystick(paddle_x: isize, target: isize) -> isize {
+    (target - paddle_x).signum()
+}
+
+/// How many game events `autoplay` allows before giving up,
+/// so a broken paddle AI can't leave the game running forever.
+const MAX_GAME_EVENTS: usize = 1_000_000;
+
+/// Let the AI play, drawing the board each time the ball moves if `visualize` is set.
+fn part_2(mut cpu: IntcodeComputer, strategy: Strategy, visualize: bool) -> Result<()> {
+    hack_quarters(&mut cpu);
+    let mut game = Game::new(cpu);
+
+    println!(
+        "{}",
+        autoplay(&mut game, strategy, visualize, MAX_GAME_EVENTS)?
+    );
+    Ok(())
 }
-fn part_2(mut cpu: IntcodeComputer) -> Result<()> {
-    hack_quarters(&mut cpu.memory);
-    let mut game = Game(cpu);
-    
+
+/// Move the paddle where `strategy` says until the game halts, giving the final score.
+/// Fails with the score so far after `max_events` game events.
+fn autoplay(
+    game: &mut Game,
+    strategy: Strategy,
+    visualize: bool,
+    max_events: usize,
+) -> Result<isize> {
     let mut score = 0;
-    let mut paddle_x: Option<isize> = None;
-    let mut ball_x: Option<isize> = None;
-    loop {
-        let mut input = || { Some(if let (Some(paddle_x), Some(ball_x)) = (paddle_x, ball_x) {
-            (paddle_x - ball_x).signum()
-        } else {
-            0
-        }) };
+    let mut paddle: Option<(isize, isize)> = None;
+    let mut target: Option<isize> = None;
+    for _ in 0..max_events {
+        let mut input = || match (paddle, target) {
+            (Some((paddle_x, _)), Some(target)) => Some(joystick(paddle_x, target)),
+            _ => Some(0),
+        };
         match game.execute(&mut input)? {
-            GameEvent::BallPos(x) => {
-                ball_x = Some(x);
+            GameEvent::BallPos { pos, velocity } => {
+                target = Some(match (strategy, paddle, game.inner_columns()) {
+                    (Strategy::Predict, Some((_, paddle_y)), Some(columns)) => {
+                        landing_x(pos, velocity, paddle_y, columns)
+                    }
+                    _ => pos.0,
+                });
+                if visualize {
+                    game.render();
+                    thread::sleep(Duration::from_millis(20));
+                }
             }
-            GameEvent::PaddlePos(x) => {
-                paddle_x = Some(x);
+            GameEvent::PaddlePos(pos) => {
+                paddle = Some(pos);
             }
             GameEvent::UpdateScore(x) => {
                 score = x;
             }
-            GameEvent::Halted => {
-                println!("{}", score);
-                break
-            }
+            GameEvent::Halted => return Ok(score),
         }
     }
 
-    Ok(())
+    bail!(
+        "Gave up after {} game events with a score of {}",
+        max_events,
+        score
+    )
+}
+
+#[cfg(feature = "interactive")]
+use interactive::play;
+
+#[cfg(not(feature = "interactive"))]
+fn play(_cpu: IntcodeComputer) -> Result<()> {
+    bail!("Playing by hand needs the interactive feature")
+}
+
+/// The program's path, defaulting to `input`, and any `--` flags.
+fn parse_args() -> (String, Vec<String>) {
+    let (flags, paths): (Vec<String>, Vec<String>) =
+        std::env::args().skip(1).partition(|arg| arg.starts_with("--"));
+    let path = paths.into_iter().next().unwrap_or_else(|| "input".to_owned());
+    (path, flags)
 }
 
 fn main() -> Result<()> {
-    let mut prog = first_arg_to_prog()?;
+    let (path, flags) = parse_args();
+    let prog = from_file(path)?;
     let cpu = IntcodeComputer::new(prog);
 
-    if !cfg!(feature = "part2") {
+    if flags.iter().any(|flag| flag == "--interactive") {
+        play(cpu)?;
+    } else if !cfg!(feature = "part2") {
         part_1(cpu)?;
     } else {
-        part_2(cpu)?;
+        let strategy = if flags.iter().any(|flag| flag == "--predict") {
+            Strategy::Predict
+        } else {
+            Strategy::Follow
+        };
+        part_2(cpu, strategy, flags.iter().any(|flag| flag == "--visualize"))?;
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watchdog() {
+        // draws a paddle at (0, 0) forever, with no ball to chase
+        let cpu = IntcodeComputer::new(vec![104, 0, 104, 0, 104, 3, 1105, 1, 0]);
+        let mut game = Game::new(cpu);
+
+        let err = autoplay(&mut game, Strategy::Follow, false, 100).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Gave up after 100 game events with a score of 0"
+        );
+    }
+
+    /// A board between walls at x = 0 and 6, with the paddle starting at (3, 5)
+    /// and the ball drawn at each of `path` in turn, the paddle moving between frames.
+    /// The score is 1000 if the paddle ends up under the ball's last position, or 0.
+    fn catch_board(path: &[(isize, isize)]) -> Vec<isize> {
+        let mut program = vec![
+            1105, 1, 5, // jump over the paddle's x at [3] and the joystick at [4]
+            3, 0, //
+            104, 0, 104, 0, 104, 1, // wall at (0, 0)
+            104, 6, 104, 0, 104, 1, // wall at (6, 0)
+            4, 3, 104, 5, 104, 3, // paddle at ([3], 5)
+        ];
+        for (frame, &(x, y)) in path.iter().enumerate() {
+            if frame > 0 {
+                program.extend(vec![
+                    3, 4, // read the joystick
+                    1, 3, 4, 3, // move the paddle
+                    4, 3, 104, 5, 104, 3, // paddle at ([3], 5)
+                ]);
+            }
+            program.extend(vec![104, x, 104, y, 104, 4]);
+        }
+        let (last_x, _) = path[path.len() - 1];
+        program.extend(vec![
+            1008, 3, last_x, 4, // [4] = whether the paddle is under the ball
+            1002, 4, 1000, 4, // [4] *= 1000
+            104, -1, 104, 0, 4, 4, // score
+            99,
+        ]);
+        program
+    }
+
+    fn autoplay_score(path: &[(isize, isize)], strategy: Strategy) -> isize {
+        let mut game = Game::new(IntcodeComputer::new(catch_board(path)));
+        autoplay(&mut game, strategy, false, 100).unwrap()
+    }
+
+    #[test]
+    fn autoplay_straight_down() {
+        let path = [(2, 1), (2, 2), (2, 3), (2, 4)];
+
+        assert_eq!(autoplay_score(&path, Strategy::Follow), 1000);
+        assert_eq!(autoplay_score(&path, Strategy::Predict), 1000);
+    }
+
+    #[test]
+    fn autoplay_off_the_wall() {
+        // bounces off the right wall, back to where the paddle should wait
+        let path = [(3, 1), (4, 2), (5, 3), (4, 4)];
+
+        assert_eq!(autoplay_score(&path, Strategy::Follow), 0);
+        assert_eq!(autoplay_score(&path, Strategy::Predict), 1000);
+    }
+
+    #[test]
+    fn blocks() {
+        // three blocks, one of them drawn over with a wall, and a score
+        let cpu = IntcodeComputer::new(vec![
+            104, 1, 104, 1, 104, 2, // block at (1, 1)
+            104, 2, 104, 1, 104, 2, // block at (2, 1)
+            104, 2, 104, 1, 104, 1, // wall at (2, 1)
+            104, 3, 104, 1, 104, 2, // block at (3, 1)
+            104, -1, 104, 0, 104, 7, // score
+            99,
+        ]);
+
+        assert_eq!(count_blocks(cpu).unwrap(), 2);
+    }
+
+    #[test]
+    fn glyphs() {
+        use Tile::*;
+
+        let glyphs: String = [Empty, Wall, Block, Paddle, Ball]
+            .iter()
+            .map(char::from)
+            .collect();
+        if cfg!(feature = "ascii") {
+            assert_eq!(glyphs, " |#=o");
+        } else {
+            assert_eq!(glyphs, " █▒▬●");
+        }
+    }
+
+    #[test]
+    fn joystick_toward_target() {
+        assert_eq!(joystick(5, 3), -1);
+        assert_eq!(joystick(3, 5), 1);
+        assert_eq!(joystick(4, 4), 0);
+    }
+
+    #[test]
+    fn landing() {
+        // straight down to the row above the paddle
+        assert_eq!(landing_x((5, 10), (1, 1), 20, (1, 40)), 14);
+        // off the right wall and back
+        assert_eq!(landing_x((35, 10), (1, 1), 20, (1, 40)), 36);
+        // off the left wall and back
+        assert_eq!(landing_x((3, 10), (-1, 1), 20, (1, 40)), 8);
+        // going up, so stay under it
+        assert_eq!(landing_x((5, 10), (1, -1), 20, (1, 40)), 5);
+    }
+}
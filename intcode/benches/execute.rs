@@ -0,0 +1,53 @@
+//! Rough timings for the interpreter's hot loop.
+//! Run with `cargo bench -p intcode`.
+
+use intcode::IntcodeComputer;
+use std::time::Instant;
+
+/// Outputs a copy of itself.
+const QUINE: [isize; 16] = [
+    109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+];
+
+/// Counts `[20]` down to zero, adding each value to `[21]`, then outputs the sum.
+fn countdown(n: isize) -> Vec<isize> {
+    let mut program = vec![
+        1001, 20, -1, 20, // [20] -= 1
+        1, 20, 21, 21, // [21] += [20]
+        1005, 20, 0, // loop while [20] != 0
+        4, 21, // output the sum
+        99,
+    ];
+    program.resize(20, 0);
+    program.extend(vec![n, 0]);
+    program
+}
+
+/// Counts `[30]` up to `[31]`, comparing and jumping on every iteration.
+fn count_up(n: isize) -> Vec<isize> {
+    let mut program = vec![
+        1001, 30, 1, 30, // [30] += 1
+        7, 30, 31, 32, // [32] = [30] < [31]
+        1005, 32, 0, // loop while [32] != 0
+        4, 30, // output the count
+        99,
+    ];
+    program.resize(30, 0);
+    program.extend(vec![0, n, 0]);
+    program
+}
+
+/// Run `program` to completion `iterations` times, printing the average time per run.
+fn bench(name: &str, program: &[isize], iterations: u32) {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        IntcodeComputer::from(program).run_collect(None).unwrap();
+    }
+    println!("{:<12} {:>12?} per run", name, start.elapsed() / iterations);
+}
+
+fn main() {
+    bench("quine", &QUINE, 100_000);
+    bench("countdown", &countdown(1_000_000), 10);
+    bench("count up", &count_up(1_000_000), 10);
+}
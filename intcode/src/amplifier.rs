@@ -0,0 +1,89 @@
+use crate::{Event, IntcodeComputer};
+use anyhow::{format_err, Result};
+use std::sync::Arc;
+
+/// A ring of amplifiers, each running its own copy of a program.
+/// Every amplifier's output is the next one's input, and the last feeds back into the first.
+pub struct AmplifierChain {
+    amps: Vec<IntcodeComputer>,
+}
+
+impl AmplifierChain {
+    /// One amplifier per phase, each given its phase as its first input.
+    /// They share one copy of the program until they write to it.
+    pub fn new(program: &[isize], phases: &[isize]) -> AmplifierChain {
+        let program: Arc<[isize]> = Arc::from(program);
+        let amps = phases
+            .iter()
+            .map(|&phase| {
+                let mut cpu = IntcodeComputer::from_shared(Arc::clone(&program));
+                cpu.push_input(phase);
+                cpu
+            })
+            .collect();
+
+        AmplifierChain { amps }
+    }
+
+    /// Feed `initial_signal` to the first amplifier and pass signals around the ring
+    /// until the last amplifier halts, giving the last signal it sent.
+    /// Fails if an amplifier asks for more input before sending anything.
+    pub fn run(&mut self, initial_signal: isize) -> Result<isize> {
+        let last = self.amps.len().saturating_sub(1);
+        let mut signal = initial_signal;
+
+        for idx in (0..self.amps.len()).cycle() {
+            let amp = &mut self.amps[idx];
+            amp.push_input(signal);
+            loop {
+                match amp.execute_buffered()? {
+                    Event::HaveOutput(x) => {
+                        signal = x;
+                        break;
+                    }
+                    Event::Halted if idx == last => return Ok(signal),
+                    Event::Halted => break,
+                    Event::Breakpoint(_) | Event::SelfModified { .. } => continue,
+                    Event::RequestingInput => {
+                        return Err(format_err!("Amplifier {} starved for input", idx))
+                    }
+                }
+            }
+        }
+
+        Ok(signal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AmplifierChain;
+
+    #[test]
+    fn feedback_example() {
+        let program = [
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+
+        let mut chain = AmplifierChain::new(&program, &[9, 8, 7, 6, 5]);
+        assert_eq!(chain.run(0).unwrap(), 139629729);
+    }
+
+    #[test]
+    fn single_pass() {
+        let program = [
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+
+        let mut chain = AmplifierChain::new(&program, &[4, 3, 2, 1, 0]);
+        assert_eq!(chain.run(0).unwrap(), 43210);
+    }
+
+    #[test]
+    fn starved() {
+        // wants a third input but never gives output
+        let mut chain = AmplifierChain::new(&[3, 0, 3, 0, 3, 0, 99], &[0]);
+        assert!(chain.run(0).is_err());
+    }
+}
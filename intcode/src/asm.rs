@@ -0,0 +1,251 @@
+use crate::{Mode, Opcode};
+use anyhow::{ensure, format_err, Result};
+use std::iter::Peekable;
+use std::str::FromStr;
+
+/// Build a program from mnemonics, for writing test programs readably.
+/// Instructions are separated by `;`, and each takes its opcode's parameters:
+///
+/// - `r9` is position mode, the cell at address 9
+/// - `b9` or `b-9` is relative mode, 9 or -9 from the relative base
+/// - a bare number like `5` or `-5` is immediate mode
+///
+/// `DATA` followed by numbers puts those numbers in the program as they are.
+/// Panics if the program doesn't assemble.
+///
+/// ```
+/// use intcode::intcode;
+///
+/// // output 1 if the input is 8, otherwise 0
+/// let program = intcode!(STR r9; EQ r9 8 r9; OUT r9; HLT);
+/// assert_eq!(program, vec![3, 9, 1008, 9, 8, 9, 4, 9, 99]);
+///
+/// let program = intcode!(BAS 10; ADD b-1 r8 b0; HLT; DATA 7 0);
+/// assert_eq!(program, vec![109, 10, 20201, -1, 8, 0, 99, 7, 0]);
+/// ```
+#[macro_export]
+macro_rules! intcode {
+    ($($token:tt)*) => {
+        $crate::assemble_tokens(&[$(stringify!($token)),*]).expect("invalid intcode! program")
+    };
+}
+
+/// What `intcode!` expands to: assemble a program from its tokens, one per element.
+#[doc(hidden)]
+pub fn assemble_tokens(tokens: &[&str]) -> Result<Vec<isize>> {
+    let mut tokens = tokens.iter().copied().peekable();
+    let mut program = Vec::new();
+
+    while let Some(mnemonic) = tokens.next() {
+        if mnemonic == ";" {
+            continue;
+        }
+
+        let mut params = Vec::new();
+        while let Some(&token) = tokens.peek() {
+            if token == ";" {
+                break;
+            }
+            params.push(operand(&mut tokens)?);
+        }
+
+        encode(mnemonic, params, &mut program)?;
+    }
+
+    Ok(program)
+}
+
+/// Assemble a program from the disassembler's format, one instruction per line,
+/// like `ADD [pos 1] [imm 2] [pos 3]` or `DATA 5 6`.
+/// The address prefix `disassemble` adds (`0004: `) is optional and ignored,
+/// as are blank lines.
+pub fn assemble(source: &str) -> Result<Vec<isize>> {
+    let mut program = Vec::new();
+
+    for (idx, line) in source.lines().enumerate() {
+        assemble_line(line, &mut program).map_err(|e| format_err!("Line {}: {}", idx + 1, e))?;
+    }
+
+    Ok(program)
+}
+
+fn assemble_line(line: &str, program: &mut Vec<isize>) -> Result<()> {
+    let mut line = line.trim();
+    let mut prefix = line.splitn(2, ':');
+    if let (Some(addr), Some(rest)) = (prefix.next(), prefix.next()) {
+        if addr.chars().all(|c| c.is_ascii_digit()) {
+            line = rest.trim();
+        }
+    }
+    if line.is_empty() {
+        return Ok(());
+    }
+
+    let mut words = line.splitn(2, char::is_whitespace);
+    let mnemonic = words.next().unwrap_or_default();
+    let rest = words.next().unwrap_or_default().trim();
+
+    let params = if mnemonic == "DATA" {
+        rest.split_whitespace()
+            .map(|value| {
+                isize::from_str(value)
+                    .map(|value| (Mode::Immediate, value))
+                    .map_err(|_| format_err!("Bad value {:?}", value))
+            })
+            .collect::<Result<_>>()?
+    } else {
+        rest.split(']')
+            .map(str::trim)
+            .filter(|param| !param.is_empty())
+            .map(bracketed)
+            .collect::<Result<_>>()?
+    };
+
+    encode(mnemonic, params, program)
+}
+
+/// A parameter like `[pos 5]`, missing its closing bracket.
+fn bracketed(param: &str) -> Result<(Mode, isize)> {
+    let bad = || format_err!("Bad parameter {:?}", format!("{}]", param));
+    let mut words = param.strip_prefix('[').ok_or_else(bad)?.split_whitespace();
+
+    let mode = words.next().ok_or_else(bad)?;
+    let mode = Mode::from_str(mode).map_err(|_| bad())?;
+    let value = words.next().and_then(|value| isize::from_str(value).ok());
+    match (value, words.next()) {
+        (Some(value), None) => Ok((mode, value)),
+        _ => Err(bad()),
+    }
+}
+
+/// Append an instruction, or for `DATA`, its values as they are.
+fn encode(mnemonic: &str, params: Vec<(Mode, isize)>, program: &mut Vec<isize>) -> Result<()> {
+    if mnemonic == "DATA" {
+        for (mode, value) in params {
+            ensure!(mode == Mode::Immediate, "DATA can only hold numbers");
+            program.push(value);
+        }
+        return Ok(());
+    }
+
+    let opcode = Opcode::from_str(mnemonic)?;
+    ensure!(
+        params.len() == opcode.param_count(),
+        "{} takes {} parameters, got {}",
+        mnemonic,
+        opcode.param_count(),
+        params.len()
+    );
+
+    let modes = params
+        .iter()
+        .zip(&[100, 1000, 10000])
+        .map(|((mode, _), place)| *mode as isize * place)
+        .sum::<isize>();
+    program.push(opcode as isize + modes);
+    program.extend(params.into_iter().map(|(_, value)| value));
+    Ok(())
+}
+
+/// One parameter's mode and value, which takes extra tokens if it's negative.
+fn operand<'a>(tokens: &mut Peekable<impl Iterator<Item = &'a str>>) -> Result<(Mode, isize)> {
+    let token = tokens
+        .next()
+        .ok_or_else(|| format_err!("Expected a parameter"))?;
+
+    match token {
+        "-" => Ok((Mode::Immediate, -number_after(token, tokens)?)),
+        "b" => {
+            ensure!(
+                tokens.next() == Some("-"),
+                "Expected b followed by an offset"
+            );
+            Ok((Mode::Relative, -number_after(token, tokens)?))
+        }
+        _ => {
+            let (mode, digits) = match token.chars().next() {
+                Some('r') => (Mode::Position, &token[1..]),
+                Some('b') => (Mode::Relative, &token[1..]),
+                _ => (Mode::Immediate, token),
+            };
+            let value = isize::from_str(digits)
+                .map_err(|_| format_err!("Unknown parameter {:?}", token))?;
+            Ok((mode, value))
+        }
+    }
+}
+
+fn number_after<'a>(prefix: &str, mut tokens: impl Iterator<Item = &'a str>) -> Result<isize> {
+    let digits = tokens
+        .next()
+        .ok_or_else(|| format_err!("Expected a number after {:?}", prefix))?;
+    isize::from_str(digits)
+        .map_err(|_| format_err!("Expected a number after {:?}, got {:?}", prefix, digits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assemble, assemble_tokens};
+    use crate::{disassemble_string, Event, IntcodeComputer};
+
+    #[test]
+    fn runs() {
+        let program = intcode!(STR r9; EQ r9 8 r9; OUT r9; HLT);
+        let mut cpu = IntcodeComputer::new(program);
+
+        assert_eq!(cpu.execute(&mut || Some(8)).unwrap(), Event::HaveOutput(1));
+    }
+
+    #[test]
+    fn negative_operands() {
+        assert_eq!(
+            intcode!(ADD -1 b-2 r3; JIT 1 -4),
+            vec![2101, -1, -2, 3, 1105, 1, -4]
+        );
+    }
+
+    #[test]
+    fn bad_programs() {
+        // wrong parameter count
+        assert!(assemble_tokens(&["OUT", "r1", "r2"]).is_err());
+        // unknown mnemonic
+        assert!(assemble_tokens(&["NOP"]).is_err());
+        // unknown parameter
+        assert!(assemble_tokens(&["OUT", "x1"]).is_err());
+        // DATA with a mode
+        assert!(assemble_tokens(&["DATA", "r1"]).is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let fixtures: &[&[isize]] = &[
+            &[3, 0, 4, 0, 99],
+            &[1002, 4, 3, 4, 33],
+            &[
+                109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+            ],
+            &[3, 3, 1108, -1, 8, 3, 4, 3, 99],
+            &[1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50],
+            &[104, 5, 42, 1, 2],
+        ];
+
+        for &program in fixtures {
+            assert_eq!(assemble(&disassemble_string(program)).unwrap(), program);
+        }
+    }
+
+    #[test]
+    fn assemble_errors() {
+        assert_eq!(
+            assemble("ADD [imm 1] [imm 2] [pos 0]\nHLT").unwrap(),
+            vec![1101, 1, 2, 0, 99]
+        );
+
+        let err = assemble("HLT\nOUT [pso 5]").unwrap_err().to_string();
+        assert!(err.contains("Line 2"));
+        assert!(err.contains("[pso 5]"));
+
+        let err = assemble("NOP [pos 1]").unwrap_err().to_string();
+        assert!(err.contains("NOP"));
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{Int, IntcodeComputer, Memory, Opcode};
+use std::collections::{HashMap, HashSet};
+
+/// Sets up an `IntcodeComputer`'s options before it's built,
+/// made by `IntcodeComputer::builder`.
+pub struct IntcodeComputerBuilder<T = isize> {
+    program: Vec<T>,
+    sparse: bool,
+    checked: bool,
+    memory_limit: Option<usize>,
+    breakpoints: HashSet<usize>,
+    detect_self_modification: bool,
+    cycle_costs: HashMap<Opcode, u64>,
+    record_rel_base: bool,
+}
+
+impl<T: Int> IntcodeComputerBuilder<T> {
+    pub fn new(program: Vec<T>) -> IntcodeComputerBuilder<T> {
+        IntcodeComputerBuilder {
+            program,
+            sparse: false,
+            checked: false,
+            memory_limit: None,
+            breakpoints: HashSet::new(),
+            detect_self_modification: false,
+            cycle_costs: HashMap::new(),
+            record_rel_base: false,
+        }
+    }
+
+    /// Make the program's writes at or past `limit` errors instead of growing memory.
+    pub fn memory_limit(mut self, limit: usize) -> IntcodeComputerBuilder<T> {
+        self.memory_limit = Some(limit);
+        self
+    }
+
+    /// Make ADD and MUL error on overflow instead of wrapping.
+    pub fn checked_arithmetic(mut self) -> IntcodeComputerBuilder<T> {
+        self.checked = true;
+        self
+    }
+
+    /// Back the computer with sparse memory,
+    /// for programs that write to very high addresses.
+    pub fn sparse(mut self) -> IntcodeComputerBuilder<T> {
+        self.sparse = true;
+        self
+    }
+
+    /// Stop with `Event::Breakpoint` when the pc reaches `addr`.
+    pub fn breakpoint(mut self, addr: usize) -> IntcodeComputerBuilder<T> {
+        self.breakpoints.insert(addr);
+        self
+    }
+
+    /// Report writes at or behind the pc with `Event::SelfModified`,
+    /// to catch a program accidentally overwriting its own instructions.
+    pub fn detect_self_modification(mut self) -> IntcodeComputerBuilder<T> {
+        self.detect_self_modification = true;
+        self
+    }
+
+    /// Count these costs in `IntcodeComputer::cycles`
+    /// instead of `Opcode::default_cost`, for the opcodes given.
+    pub fn cycle_costs(mut self, costs: HashMap<Opcode, u64>) -> IntcodeComputerBuilder<T> {
+        self.cycle_costs = costs;
+        self
+    }
+
+    /// Keep every change `BAS` makes to the relative base,
+    /// for `IntcodeComputer::rel_base_history`.
+    pub fn record_rel_base(mut self) -> IntcodeComputerBuilder<T> {
+        self.record_rel_base = true;
+        self
+    }
+
+    pub fn build(self) -> IntcodeComputer<T> {
+        let memory = if self.sparse {
+            Memory::sparse(self.program)
+        } else {
+            Memory::from(self.program)
+        };
+
+        let mut cpu = IntcodeComputer::with_memory(memory);
+        cpu.checked = self.checked;
+        cpu.max_memory = self.memory_limit;
+        cpu.breakpoints = self.breakpoints;
+        cpu.detect_self_modification = self.detect_self_modification;
+        cpu.cycle_costs = self.cycle_costs;
+        if self.record_rel_base {
+            cpu.rel_base_history = Some(Vec::new());
+        }
+        cpu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IntcodeComputerBuilder;
+    use crate::Event;
+
+    #[test]
+    fn checked_and_capped() {
+        let mut cpu = IntcodeComputerBuilder::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50])
+            .checked_arithmetic()
+            .memory_limit(12)
+            .build();
+
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Event::Halted);
+        assert_eq!(cpu.peek(0), 3500);
+    }
+
+    #[test]
+    fn sparse_with_breakpoint() {
+        let mut cpu = IntcodeComputerBuilder::new(vec![104, 1, 104, 2, 99])
+            .sparse()
+            .breakpoint(2)
+            .build();
+
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Event::HaveOutput(1));
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Event::Breakpoint(2));
+        assert_eq!(cpu.run_collect(None).unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn custom_cycle_costs() {
+        use crate::Opcode;
+
+        let costs = vec![(Opcode::ADD, 10), (Opcode::HLT, 0)]
+            .into_iter()
+            .collect();
+        let mut cpu = IntcodeComputerBuilder::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50])
+            .cycle_costs(costs)
+            .build();
+
+        cpu.run_collect(None).unwrap();
+        // ADD from the table, MUL by default, HLT from the table
+        assert_eq!(cpu.cycles(), 10 + 5);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{Event, IntcodeComputer};
+use anyhow::{format_err, Result};
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+/// A computer that reads its input from a channel and sends its output to another,
+/// so several can run on their own threads and be wired together.
+pub struct ChannelComputer {
+    cpu: IntcodeComputer,
+    rx: Receiver<isize>,
+    tx: Sender<isize>,
+}
+
+impl ChannelComputer {
+    pub fn new(cpu: IntcodeComputer, rx: Receiver<isize>, tx: Sender<isize>) -> ChannelComputer {
+        ChannelComputer { cpu, rx, tx }
+    }
+
+    /// Run until halted, blocking on the input channel whenever the program needs a value.
+    /// Fails if the input channel's senders are all gone, or nobody is listening for output.
+    pub fn run(mut self) -> Result<()> {
+        let rx = &self.rx;
+        loop {
+            match self.cpu.execute(&mut || rx.recv().ok())? {
+                Event::HaveOutput(x) => self
+                    .tx
+                    .send(x)
+                    .map_err(|_| format_err!("Output channel closed while sending {}", x))?,
+                Event::Breakpoint(_) | Event::SelfModified { .. } => continue,
+                Event::Halted => break Ok(()),
+                Event::RequestingInput => break Err(format_err!("Input channel closed")),
+            }
+        }
+    }
+
+    /// Run `program` on its own thread, reading from `rx` and writing to `tx`.
+    pub fn spawn(
+        program: Vec<isize>,
+        rx: Receiver<isize>,
+        tx: Sender<isize>,
+    ) -> JoinHandle<Result<()>> {
+        thread::spawn(move || ChannelComputer::new(IntcodeComputer::new(program), rx, tx).run())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChannelComputer;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn bounce_between_threads() {
+        // add 1, then double
+        let increment = vec![3, 9, 101, 1, 9, 9, 4, 9, 99, 0];
+        let double = vec![3, 9, 102, 2, 9, 9, 4, 9, 99, 0];
+
+        let (to_first, first_rx) = channel();
+        let (first_tx, second_rx) = channel();
+        let (second_tx, from_second) = channel();
+
+        let first = ChannelComputer::spawn(increment, first_rx, first_tx);
+        let second = ChannelComputer::spawn(double, second_rx, second_tx);
+
+        to_first.send(5).unwrap();
+        assert_eq!(from_second.recv().unwrap(), 12);
+
+        first.join().unwrap().unwrap();
+        second.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn closed_input() {
+        let (to_cpu, rx) = channel();
+        let (tx, _from_cpu) = channel();
+        drop(to_cpu);
+
+        let handle = ChannelComputer::spawn(vec![3, 0, 99], rx, tx);
+
+        assert!(handle.join().unwrap().is_err());
+    }
+}
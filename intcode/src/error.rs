@@ -0,0 +1,59 @@
+use crate::{Int, Opcode};
+use thiserror::Error;
+
+/// What can go wrong decoding or running a program,
+/// for callers that want to tell the kinds apart.
+/// Values from memory are widened to `i128` so they fit whatever `Int` the computer uses.
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum IntcodeError {
+    #[error("Unknown opcode {0}")]
+    UnknownOpcode(usize),
+    #[error("Unknown mode type {0}")]
+    UnknownMode(usize),
+    #[error("Int {0} was negative when decoding operation")]
+    NegativeInstruction(i128),
+    #[error("Int {0} is too large to decode as an operation")]
+    InstructionTooLarge(i128),
+    #[error("Nonzero mode for unused parameter in {0}")]
+    UnusedMode(usize),
+    #[error("illegal negative address {addr} from arg {param} at pc {pc}")]
+    IllegalAddress { addr: i128, param: usize, pc: usize },
+    #[error("address {addr} from arg {param} at pc {pc} is too large")]
+    AddressTooLarge { addr: i128, param: usize, pc: usize },
+    #[error("argument address {addr} out of bounds (memory len {len}) at pc {pc}")]
+    ArgumentOutOfBounds { addr: usize, len: usize, pc: usize },
+    #[error("Can't store in an immediate at pc {pc}")]
+    ImmediateStore { pc: usize },
+    #[error("write to address {addr} exceeds memory limit {limit} at pc {pc}")]
+    MemoryLimit {
+        addr: usize,
+        limit: usize,
+        pc: usize,
+    },
+    #[error("{opcode:?} overflowed at pc {pc}: {left} {} {right}", operator(.opcode))]
+    Overflow {
+        opcode: Opcode,
+        pc: usize,
+        left: i128,
+        right: i128,
+    },
+    #[error("pc {pc} out of bounds (memory len {len})")]
+    PcOutOfBounds { pc: usize, len: usize },
+    #[error("Step limit of {limit} exceeded at pc {pc}")]
+    StepLimit { limit: u64, pc: usize },
+    #[error("Program requested input at pc {pc}")]
+    InputExhausted { pc: usize },
+}
+
+fn operator(opcode: &Opcode) -> &'static str {
+    match opcode {
+        Opcode::MUL => "*",
+        _ => "+",
+    }
+}
+
+/// Widen a value from memory for an error.
+/// Every primitive signed integer fits in an `i128`.
+pub(crate) fn wide<T: Int>(int: T) -> i128 {
+    int.to_i128().unwrap_or_default()
+}
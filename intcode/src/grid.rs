@@ -0,0 +1,145 @@
+use std::collections::hash_map::{Entry, HashMap};
+
+/// Cells at integer coordinates, like the panels a robot paints or the tiles of a screen.
+/// It covers whatever has been inserted, so coordinates can be negative.
+///
+/// By default y grows downward like a screen. Use `Grid::y_up` for y growing upward,
+/// and rendering will flip it so the top row comes first.
+#[derive(Debug, Clone)]
+pub struct Grid<T> {
+    cells: HashMap<(isize, isize), T>,
+    y_up: bool,
+}
+
+impl<T> Default for Grid<T> {
+    fn default() -> Self {
+        Grid::new()
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn new() -> Grid<T> {
+        Grid {
+            cells: HashMap::new(),
+            y_up: false,
+        }
+    }
+
+    /// A grid where y grows upward, like a compass's north.
+    pub fn y_up() -> Grid<T> {
+        Grid {
+            y_up: true,
+            ..Grid::new()
+        }
+    }
+
+    pub fn insert(&mut self, coords: (isize, isize), value: T) -> Option<T> {
+        self.cells.insert(coords, value)
+    }
+
+    pub fn get(&self, coords: (isize, isize)) -> Option<&T> {
+        self.cells.get(&coords)
+    }
+
+    pub fn entry(&mut self, coords: (isize, isize)) -> Entry<'_, (isize, isize), T> {
+        self.cells.entry(coords)
+    }
+
+    /// How many cells have been inserted.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&(isize, isize), &T)> {
+        self.cells.iter()
+    }
+
+    /// The smallest and largest coordinates inserted, as `((min_x, min_y), (max_x, max_y))`.
+    /// `None` if the grid is empty.
+    pub fn bounds(&self) -> Option<((isize, isize), (isize, isize))> {
+        let xs = self.cells.keys().map(|&(x, _)| x);
+        let ys = self.cells.keys().map(|&(_, y)| y);
+        Some((
+            (xs.clone().min()?, ys.clone().min()?),
+            (xs.max()?, ys.max()?),
+        ))
+    }
+}
+
+impl<T: Clone + Default> Grid<T> {
+    /// Every cell within the bounds, a row at a time from the top,
+    /// with cells that were never inserted as `T::default()`.
+    pub fn rows(&self) -> Vec<Vec<T>> {
+        let ((min_x, min_y), (max_x, max_y)) = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return Vec::new(),
+        };
+
+        let mut rows: Vec<Vec<T>> = (min_y..=max_y)
+            .map(|y| {
+                (min_x..=max_x)
+                    .map(|x| self.get((x, y)).cloned().unwrap_or_default())
+                    .collect()
+            })
+            .collect();
+        if self.y_up {
+            rows.reverse();
+        }
+        rows
+    }
+
+    /// Draw each cell as a character, with a newline after every row.
+    pub fn render(&self, draw: impl Fn(&T) -> char) -> String {
+        let mut out = String::new();
+        for row in self.rows() {
+            out.extend(row.iter().map(&draw));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Grid;
+
+    fn draw(&lit: &bool) -> char {
+        if lit {
+            '#'
+        } else {
+            '.'
+        }
+    }
+
+    #[test]
+    fn render() {
+        let mut grid = Grid::new();
+        grid.insert((-1, 0), true);
+        grid.insert((1, 0), true);
+        grid.insert((0, 1), true);
+
+        assert_eq!(grid.bounds(), Some(((-1, 0), (1, 1))));
+        assert_eq!(grid.render(draw), "#.#\n.#.\n");
+    }
+
+    #[test]
+    fn render_y_up() {
+        let mut grid = Grid::y_up();
+        grid.insert((0, 0), true);
+        grid.insert((1, 1), true);
+
+        assert_eq!(grid.render(draw), ".#\n#.\n");
+    }
+
+    #[test]
+    fn empty() {
+        let grid: Grid<bool> = Grid::new();
+
+        assert_eq!(grid.bounds(), None);
+        assert_eq!(grid.render(draw), "");
+    }
+}
@@ -1,23 +1,61 @@
 use anyhow::{self, ensure, format_err, Error, Result};
 use num_derive::FromPrimitive;
-use num_traits::FromPrimitive;
+use num_traits::{FromPrimitive, PrimInt, Signed, WrappingAdd, WrappingMul};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
+use std::fmt;
 use std::fs::read_to_string;
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::Arc;
+
+mod amplifier;
+mod asm;
+mod builder;
+mod channel;
+mod error;
+mod grid;
+mod memory;
+mod network;
+mod permutations;
+
+pub use amplifier::AmplifierChain;
+pub use asm::{assemble, assemble_tokens};
+pub use builder::IntcodeComputerBuilder;
+pub use channel::ChannelComputer;
+use error::wide;
+pub use error::IntcodeError;
+pub use grid::Grid;
+pub use memory::Memory;
+pub use network::{Network, NAT_ADDRESS};
+pub use permutations::Permutations;
+
+/// The integer types a computer's memory cells can be:
+/// every signed primitive integer at least 16 bits wide.
+pub trait Int:
+    PrimInt + Signed + WrappingAdd + WrappingMul + From<u8> + fmt::Display + fmt::Debug + 'static
+{
+}
 
-pub enum InstructionType {
-    /// Three arguments
-    A,
-    /// One argument
-    I,
-    /// Two arguments
-    J,
-    /// Halt
-    H,
+impl<T> Int for T where
+    T: PrimInt
+        + Signed
+        + WrappingAdd
+        + WrappingMul
+        + From<u8>
+        + fmt::Display
+        + fmt::Debug
+        + 'static
+{
 }
 
-#[derive(FromPrimitive, Debug, PartialEq, Eq)]
+#[derive(FromPrimitive, Debug, PartialEq, Eq, Hash, Clone)]
 pub enum Opcode {
     // A-TYPE
     ADD = 1,
@@ -36,53 +74,83 @@ pub enum Opcode {
 }
 
 impl TryFrom<usize> for Opcode {
-    type Error = Error;
+    type Error = IntcodeError;
 
     fn try_from(int: usize) -> Result<Self, Self::Error> {
-        Opcode::from_usize(int).ok_or_else(|| anyhow::format_err!("Unknown opcode {}", int))
+        Opcode::from_usize(int).ok_or(IntcodeError::UnknownOpcode(int))
+    }
+}
+
+/// Parses the mnemonic, as printed by `Debug`.
+impl FromStr for Opcode {
+    type Err = Error;
+
+    fn from_str(mnemonic: &str) -> Result<Self, Self::Err> {
+        use Opcode::*;
+        Ok(match mnemonic {
+            "ADD" => ADD,
+            "MUL" => MUL,
+            "LT" => LT,
+            "EQ" => EQ,
+            "JIT" => JIT,
+            "JIF" => JIF,
+            "STR" => STR,
+            "OUT" => OUT,
+            "BAS" => BAS,
+            "HLT" => HLT,
+            _ => return Err(format_err!("Unknown mnemonic {}", mnemonic)),
+        })
     }
 }
 
 impl Opcode {
-    // fn param_count(&self) -> usize {
-    //     use Opcode::*;
-    //     match self {
-    //         ADD | MUL => 3,
-    //         STR | OUT => 1,
-    //         HLT => 0,
-    //     }
-    // }
+    /// How many parameters follow the opcode.
+    fn param_count(&self) -> usize {
+        self.lengths().0 - 1
+    }
+
     fn instruction_length(&self) -> usize {
-        use InstructionType::*;
-        match self.instruction_type() {
-            A => 4,
-            J => 3,
-            I => 2,
-            H => 0,
+        self.lengths().0
+    }
+
+    /// Which parameter the result is written through, counting from 1, if any.
+    fn store_param(&self) -> Option<usize> {
+        use Opcode::*;
+        match self {
+            ADD | MUL | LT | EQ => Some(3),
+            STR => Some(1),
+            JIT | JIF | OUT | BAS | HLT => None,
         }
     }
-    fn should_move(&self) -> usize {
-        use InstructionType::*;
-        match self.instruction_type() {
-            A => 4,
-            I => 2,
-            J | H => 0,
+
+    /// A rough idea of how long the instruction takes on real hardware,
+    /// counted by `IntcodeComputer::cycles` unless the builder was given other costs.
+    /// Arithmetic costs more than I/O, which costs more than jumps.
+    pub fn default_cost(&self) -> u64 {
+        use Opcode::*;
+        match self {
+            MUL => 5,
+            ADD => 3,
+            LT | EQ | STR | OUT => 2,
+            JIT | JIF | BAS | HLT => 1,
         }
     }
 
-    fn instruction_type(&self) -> InstructionType {
-        use InstructionType::*;
+    /// The instruction's length and how far the pc moves after it runs
+    /// (jumps move it themselves, and an `HLT` returns before the pc moves at all),
+    /// together so each instruction only has to look them up once.
+    fn lengths(&self) -> (usize, usize) {
         use Opcode::*;
         match self {
-            ADD | MUL | LT | EQ => A,
-            JIT | JIF => J,
-            STR | OUT | BAS => I,
-            HLT => H,
+            ADD | MUL | LT | EQ => (4, 4),
+            JIT | JIF => (3, 0),
+            STR | OUT | BAS => (2, 2),
+            HLT => (1, 1),
         }
     }
 }
 
-#[derive(FromPrimitive, Debug, PartialEq, Eq)]
+#[derive(FromPrimitive, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Mode {
     Position = 0,
     Immediate = 1,
@@ -90,330 +158,1653 @@ pub enum Mode {
 }
 
 impl TryFrom<usize> for Mode {
-    type Error = Error;
+    type Error = IntcodeError;
 
     fn try_from(int: usize) -> Result<Self, Self::Error> {
-        Mode::from_usize(int).ok_or_else(|| anyhow::format_err!("Unknown mode type {}", int))
+        Mode::from_usize(int).ok_or(IntcodeError::UnknownMode(int))
+    }
+}
+
+/// Parses the short names `Display` gives, like `pos`.
+impl FromStr for Mode {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        use Mode::*;
+        Ok(match name {
+            "pos" => Position,
+            "imm" => Immediate,
+            "rel" => Relative,
+            _ => return Err(format_err!("Unknown mode {}", name)),
+        })
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Mode::*;
+        f.write_str(match self {
+            Position => "pos",
+            Immediate => "imm",
+            Relative => "rel",
+        })
     }
 }
 
 pub struct Operation {
     pub opcode: Opcode,
-    pub mode1: Mode,
-    pub mode2: Mode,
-    pub mode3: Mode,
+    /// The mode of each parameter, in order. Ones the opcode doesn't take are `Position`.
+    pub modes: [Mode; 3],
+    /// The instruction word this was decoded from.
+    pub raw: isize,
 }
 
-impl TryFrom<isize> for Operation {
-    type Error = Error;
+macro_rules! operation_from_int {
+    ($($int:ty),*) => {
+        $(
+            impl TryFrom<$int> for Operation {
+                type Error = IntcodeError;
 
-    fn try_from(int: isize) -> Result<Self, Self::Error> {
-        anyhow::ensure!(
-            int.is_positive(),
-            "Int was negative when decoding operation"
-        );
-        let int = int as usize;
+                fn try_from(int: $int) -> Result<Self, Self::Error> {
+                    Operation::from_int(int)
+                }
+            }
+        )*
+    };
+}
 
-        let opcode = int % 100;
-        let opcode: Opcode = opcode.try_into()?;
-        let mode1 = ((int / 100) % 10).try_into()?;
-        let mode2 = ((int / 1000) % 10).try_into()?;
-        let mode3 = ((int / 10000) % 10).try_into()?;
+operation_from_int!(i16, i32, i64, i128, isize);
 
-        Ok(Operation {
+impl Operation {
+    fn from_int<T: Int>(int: T) -> Result<Self, IntcodeError> {
+        Instruction::decode(int)?.to_operation()
+    }
+
+    /// The mode of parameter `param`, counting from 1 like its offset from the pc.
+    pub fn mode(&self, param: usize) -> Mode {
+        self.modes[param - 1]
+    }
+
+    /// The modes of the parameters this operation actually takes.
+    fn param_modes(&self) -> impl Iterator<Item = &Mode> {
+        self.modes.iter().take(self.opcode.param_count())
+    }
+}
+
+/// An instruction with only its opcode decoded.
+/// Each parameter's mode is left in the raw word until it's needed,
+/// and decoded by `mode` for only the parameters the instruction reads or writes.
+struct Instruction {
+    opcode: Opcode,
+    word: usize,
+    length: usize,
+    should_move: usize,
+}
+
+/// What to divide an instruction word by to bring each parameter's mode digit
+/// to the ones place, indexed by the parameter's offset from the pc.
+/// The last entry is one past the widest instruction, for checking there's nothing left.
+const MODE_DIVISORS: [usize; 5] = [1, 100, 1000, 10000, 100_000];
+
+impl Instruction {
+    fn decode<T: Int>(int: T) -> Result<Instruction, IntcodeError> {
+        if int.is_negative() {
+            return Err(IntcodeError::NegativeInstruction(wide(int)));
+        }
+        let word = int
+            .to_usize()
+            .ok_or_else(|| IntcodeError::InstructionTooLarge(wide(int)))?;
+        let opcode: Opcode = (word % 100).try_into()?;
+        if word / MODE_DIVISORS[opcode.param_count() + 1] != 0 {
+            return Err(IntcodeError::UnusedMode(word));
+        }
+        let (length, should_move) = opcode.lengths();
+
+        Ok(Instruction {
             opcode,
-            mode1,
-            mode2,
-            mode3,
+            word,
+            length,
+            should_move,
+        })
+    }
+
+    /// The mode of the parameter `offset` cells past the pc.
+    fn mode(&self, offset: usize) -> Result<Mode, IntcodeError> {
+        ((self.word / MODE_DIVISORS[offset]) % 10).try_into()
+    }
+
+    /// Decode every mode, whether this instruction uses it or not.
+    fn to_operation(&self) -> Result<Operation, IntcodeError> {
+        Ok(Operation {
+            opcode: self.opcode.clone(),
+            modes: [self.mode(1)?, self.mode(2)?, self.mode(3)?],
+            raw: self.word as isize,
         })
     }
 }
 
-pub struct IntcodeComputer {
+/// Formats as the mnemonic followed by each parameter's mode,
+/// e.g. `ADD pos imm pos`.
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.opcode)?;
+        for mode in self.param_modes() {
+            write!(f, " {}", mode)?;
+        }
+        Ok(())
+    }
+}
+
+/// A snapshot of a computer that can be resumed with `IntcodeComputer::from_state`.
+/// Serializable with the `serde` feature.
+/// I/O closures and debugging configuration aren't included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct State<T = isize> {
     pub pc: usize,
-    pub rel_base: isize,
-    pub memory: Vec<isize>,
+    pub rel_base: T,
+    pub memory: Memory<T>,
+    /// Inputs queued by `push_input` that haven't been read yet.
+    pub inputs: VecDeque<T>,
+}
+
+/// Input and output closures given to `new_with_io` or `new_with_writer`.
+/// Output can fail when it's going to a writer.
+struct Io<T> {
+    input: Box<dyn FnMut() -> T>,
+    output: Box<dyn FnMut(T) -> io::Result<()>>,
+}
+
+/// A callback given to `IntcodeComputer::watch`.
+type Watcher<T> = Box<dyn FnMut(T, T)>;
+
+/// An intcode machine whose memory cells are `T`, `isize` by default.
+pub struct IntcodeComputer<T = isize> {
+    pc: usize,
+    rel_base: T,
+    pub memory: Memory<T>,
+    /// The memory as it was when the computer was created, for `reset`.
+    initial_memory: Memory<T>,
+    inputs: VecDeque<T>,
+    io: Option<Io<T>>,
+    instructions_executed: u64,
+    /// How many times each opcode has been executed, counted like `instructions_executed`.
+    opcode_counts: HashMap<Opcode, u64>,
+    /// Whether ADD and MUL error on overflow instead of wrapping.
+    checked: bool,
+    /// Writes at or past this address are errors instead of growing memory.
+    max_memory: Option<usize>,
+    breakpoints: HashSet<usize>,
+    /// The breakpoint we last stopped at, so resuming doesn't stop there again.
+    stopped_at: Option<usize>,
+    /// Whether writes at or behind the pc are reported with `Event::SelfModified`.
+    detect_self_modification: bool,
+    /// The address and pc of a self-modifying write the current instruction made.
+    self_modified: Option<(usize, usize)>,
+    /// The highest address a parameter has read or written.
+    high_water_mark: usize,
+    /// The cost of each instruction executed so far, counted like `instructions_executed`.
+    cycle_count: u64,
+    /// Costs to use instead of `Opcode::default_cost`, for the opcodes given.
+    cycle_costs: HashMap<Opcode, u64>,
+    /// Callbacks given the old and new value whenever the program writes to their address.
+    watchers: HashMap<usize, Vec<Watcher<T>>>,
+    /// `(pc, old, new)` for every `BAS` executed, if the builder turned it on.
+    rel_base_history: Option<Vec<(usize, T, T)>>,
+}
+
+/// What `IntcodeComputer::execute_with_policy` does when the program wants input
+/// and there isn't any.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum InputPolicy {
+    /// Fail with `IntcodeError::InputExhausted`.
+    #[default]
+    Error,
+    /// Give the program a 0.
+    ReturnZero,
+    /// Stop as if the program had halted. The pc stays on the input instruction.
+    Halt,
+}
+
+/// What a run to completion produced, from `IntcodeComputer::execute_until_halt`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RunSummary<T = isize> {
+    pub outputs: Vec<T>,
+    /// How many instructions ran, including the final `HLT`.
+    pub instructions: u64,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum Event {
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Event<T = isize> {
     RequestingInput,
-    HaveOutput(isize),
+    HaveOutput(T),
+    /// The program reached an `HLT`. The pc is left pointing at it,
+    /// so running again just halts again.
     Halted,
+    /// The pc reached a breakpoint. The instruction there hasn't run yet.
+    Breakpoint(usize),
+    /// With self-modification detection on, the instruction at `pc`
+    /// wrote to `addr`, which is at or behind it. The write has already happened.
+    SelfModified {
+        addr: usize,
+        pc: usize,
+    },
 }
 
-fn convert_addr(i: isize) -> Result<usize> {
-    ensure!(i.signum() != -1, "Illegal address");
-    Ok(i as usize)
-}
+impl<T: Int> IntcodeComputer<T> {
+    fn decode(&self) -> Result<Instruction, IntcodeError> {
+        if self.pc >= self.memory.len() {
+            return Err(IntcodeError::PcOutOfBounds {
+                pc: self.pc,
+                len: self.memory.len(),
+            });
+        }
+        Instruction::decode(self.memory.get(self.pc))
+    }
+
+    /// Turn an address that came from argument `offset` into an index,
+    /// rejecting negative ones.
+    fn convert_addr(&self, addr: T, offset: usize) -> Result<usize, IntcodeError> {
+        if addr.is_negative() {
+            return Err(IntcodeError::IllegalAddress {
+                addr: wide(addr),
+                param: offset,
+                pc: self.pc,
+            });
+        }
+        addr.to_usize()
+            .ok_or_else(|| IntcodeError::AddressTooLarge {
+                addr: wide(addr),
+                param: offset,
+                pc: self.pc,
+            })
+    }
 
-impl IntcodeComputer {
-    fn decode(&self) -> anyhow::Result<Operation> {
-        self.memory[self.pc].try_into()
+    /// Read the raw parameter `offset` cells past the pc.
+    fn read_param(&self, offset: usize) -> Result<T, IntcodeError> {
+        let addr = self.pc + offset;
+        if addr >= self.memory.len() {
+            return Err(IntcodeError::ArgumentOutOfBounds {
+                addr,
+                len: self.memory.len(),
+                pc: self.pc,
+            });
+        }
+        Ok(self.memory.get(addr))
     }
 
-    fn load_arg(&self, offset: usize, mode: Mode) -> Result<isize> {
+    /// The address the parameter `offset` cells past the pc refers to,
+    /// or `None` if it's an immediate.
+    fn resolve_addr(&self, offset: usize, mode: Mode) -> Result<Option<usize>, IntcodeError> {
         use Mode::*;
-        match mode {
-            Immediate => Ok(self.memory[self.pc + offset]),
-            Position => {
-                let addr = self.memory[self.pc + offset];
-                self.get_value_from_addr(addr)
-            }
+        let addr = match mode {
+            Immediate => return Ok(None),
+            Position => self.read_param(offset)?,
             Relative => {
-                let rel_base_augend = self.memory[self.pc + offset];
-                let addr = self.rel_base + rel_base_augend;
-                self.get_value_from_addr(addr)
+                let param = self.read_param(offset)?;
+                match self.rel_base.checked_add(&param) {
+                    Some(addr) => addr,
+                    None => return Err(self.relative_overflow(param, offset)),
+                }
             }
-        }
+        };
+        self.convert_addr(addr, offset).map(Some)
     }
 
-    fn store_arg(&mut self, offset: usize, mode: Mode, value: isize) -> Result<()> {
-        use Mode::*;
-        match mode {
-            Immediate => return Err(format_err!("Can't store in an immediate")),
-            Position => {
-                let addr = self.memory[self.pc + offset];
-                *self.get_ptr_from_addr(addr)? = value;
+    /// The error for a relative address too far from zero for `T`.
+    fn relative_overflow(&self, param: T, offset: usize) -> IntcodeError {
+        let addr = wide(self.rel_base) + wide(param);
+        if addr < 0 {
+            IntcodeError::IllegalAddress {
+                addr,
+                param: offset,
+                pc: self.pc,
             }
-            Relative => {
-                let rel_base_augend = self.memory[self.pc + offset];
-                let addr = self.rel_base + rel_base_augend;
-                *self.get_ptr_from_addr(addr)? = value;
+        } else {
+            IntcodeError::AddressTooLarge {
+                addr,
+                param: offset,
+                pc: self.pc,
             }
         }
-        Ok(())
     }
 
-    fn get_value_from_addr(&self, addr: isize) -> Result<isize> {
-        let idx = convert_addr(addr)?;
-        if idx >= self.memory.len() {
-            return Ok(0);
+    fn load_arg(&mut self, offset: usize, mode: Mode) -> Result<T, IntcodeError> {
+        match self.resolve_addr(offset, mode)? {
+            Some(idx) => Ok(self.get_value_from_addr(idx)),
+            None => self.read_param(offset),
+        }
+    }
+
+    /// Like `load_arg`, but without counting the read towards `max_addr_used`.
+    fn peek_arg(&self, offset: usize, mode: Mode) -> Result<T, IntcodeError> {
+        match self.resolve_addr(offset, mode)? {
+            Some(idx) => Ok(self.memory.get(idx)),
+            None => self.read_param(offset),
+        }
+    }
+
+    fn store_arg(&mut self, offset: usize, mode: Mode, value: T) -> Result<(), IntcodeError> {
+        match self.resolve_addr(offset, mode)? {
+            Some(idx) => {
+                let old = std::mem::replace(self.get_ptr_from_addr(idx)?, value);
+                if let Some(watchers) = self.watchers.get_mut(&idx) {
+                    for watcher in watchers {
+                        watcher(old, value);
+                    }
+                }
+            }
+            None => return Err(IntcodeError::ImmediateStore { pc: self.pc }),
         }
-        Ok(self.memory[idx])
+        Ok(())
     }
 
-    fn get_ptr_from_addr(&mut self, addr: isize) -> Result<&mut isize> {
-        let idx = convert_addr(addr)?;
-        if idx >= self.memory.len() {
-            self.memory.resize(idx + 1, 0);
+    fn get_value_from_addr(&mut self, idx: usize) -> T {
+        self.high_water_mark = self.high_water_mark.max(idx);
+        self.memory.get(idx)
+    }
+
+    fn get_ptr_from_addr(&mut self, idx: usize) -> Result<&mut T, IntcodeError> {
+        if let Some(limit) = self.max_memory {
+            if idx >= limit {
+                return Err(IntcodeError::MemoryLimit {
+                    addr: idx,
+                    limit,
+                    pc: self.pc,
+                });
+            }
+        }
+        if self.detect_self_modification && idx <= self.pc {
+            self.self_modified = Some((idx, self.pc));
         }
-        Ok(&mut self.memory[idx])
+        self.high_water_mark = self.high_water_mark.max(idx);
+        Ok(self.memory.get_mut(idx))
     }
 
-    pub fn new(program: Vec<isize>) -> IntcodeComputer {
+    fn with_memory(memory: Memory<T>) -> IntcodeComputer<T> {
         IntcodeComputer {
             pc: 0,
-            rel_base: 0,
-            memory: program,
+            rel_base: T::zero(),
+            initial_memory: memory.clone(),
+            memory,
+            inputs: VecDeque::new(),
+            io: None,
+            instructions_executed: 0,
+            opcode_counts: HashMap::new(),
+            checked: false,
+            max_memory: None,
+            breakpoints: HashSet::new(),
+            stopped_at: None,
+            detect_self_modification: false,
+            self_modified: None,
+            high_water_mark: 0,
+            cycle_count: 0,
+            cycle_costs: HashMap::new(),
+            watchers: HashMap::new(),
+            rel_base_history: None,
+        }
+    }
+
+    pub fn new(program: Vec<T>) -> IntcodeComputer<T> {
+        IntcodeComputer::builder(program).build()
+    }
+
+    /// Create a computer that reads `program` in place, only copying it the first time
+    /// the program writes to memory, so computers that share it don't each need a copy.
+    pub fn from_shared(program: Arc<[T]>) -> IntcodeComputer<T> {
+        IntcodeComputer::with_memory(Memory::Shared(program))
+    }
+
+    /// Start configuring a computer for `program`, for options beyond what `new` gives.
+    pub fn builder(program: Vec<T>) -> IntcodeComputerBuilder<T> {
+        IntcodeComputerBuilder::new(program)
+    }
+
+    /// Take a snapshot of the machine's state.
+    pub fn to_state(&self) -> State<T> {
+        State {
+            pc: self.pc,
+            rel_base: self.rel_base,
+            memory: self.memory.clone(),
+            inputs: self.inputs.clone(),
+        }
+    }
+
+    /// Resume a computer from a snapshot.
+    /// Its memory at the time of the snapshot is what `reset` goes back to.
+    pub fn from_state(state: State<T>) -> IntcodeComputer<T> {
+        let mut cpu = IntcodeComputer::with_memory(state.memory);
+        cpu.pc = state.pc;
+        cpu.rel_base = state.rel_base;
+        cpu.inputs = state.inputs;
+        cpu
+    }
+
+    /// Restore memory to the program the computer was created with,
+    /// and start over from the beginning.
+    /// Breakpoints and other configuration are kept.
+    pub fn reset(&mut self) {
+        self.memory = self.initial_memory.clone();
+        self.pc = 0;
+        self.rel_base = T::zero();
+        self.inputs.clear();
+        self.instructions_executed = 0;
+        self.opcode_counts.clear();
+        self.cycle_count = 0;
+        if let Some(history) = &mut self.rel_base_history {
+            history.clear();
+        }
+        self.stopped_at = None;
+        self.self_modified = None;
+        self.high_water_mark = 0;
+    }
+
+    /// Create a computer whose ADD and MUL instructions
+    /// return an error on overflow instead of wrapping.
+    pub fn new_checked(program: Vec<T>) -> IntcodeComputer<T> {
+        IntcodeComputer::builder(program)
+            .checked_arithmetic()
+            .build()
+    }
+
+    /// Make the program's writes at or past `limit` return an error
+    /// instead of growing memory. Reads past it still return 0.
+    pub fn with_memory_limit(mut self, limit: usize) -> IntcodeComputer<T> {
+        self.max_memory = Some(limit);
+        self
+    }
+
+    /// Create a computer that reads from `input` whenever the program needs a value,
+    /// and hands every output to `output`. Run it with `execute_io`.
+    pub fn new_with_io<I, O>(program: Vec<T>, input: I, mut output: O) -> IntcodeComputer<T>
+    where
+        I: FnMut() -> T + 'static,
+        O: FnMut(T) + 'static,
+    {
+        let mut cpu = IntcodeComputer::new(program);
+        cpu.io = Some(Io {
+            input: Box::new(input),
+            output: Box::new(move |value| {
+                output(value);
+                Ok(())
+            }),
+        });
+        cpu
+    }
+
+    /// Like `new_with_io`, but writing each output to `output` as a decimal line,
+    /// for sending it to a file or buffer. `execute_io` fails if a write does.
+    pub fn new_with_writer<I, W>(program: Vec<T>, input: I, mut output: W) -> IntcodeComputer<T>
+    where
+        I: FnMut() -> T + 'static,
+        W: Write + 'static,
+    {
+        let mut cpu = IntcodeComputer::new(program);
+        cpu.io = Some(Io {
+            input: Box::new(input),
+            output: Box::new(move |value| writeln!(output, "{}", value)),
+        });
+        cpu
+    }
+
+    /// Create a computer backed by sparse memory,
+    /// for programs that write to very high addresses.
+    pub fn new_sparse(program: Vec<T>) -> IntcodeComputer<T> {
+        IntcodeComputer::builder(program).sparse().build()
+    }
+
+    /// The address of the next instruction to execute.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Move the pc to `addr`, which must be within memory.
+    pub fn set_pc(&mut self, addr: usize) -> Result<(), IntcodeError> {
+        if addr >= self.memory.len() {
+            return Err(IntcodeError::PcOutOfBounds {
+                pc: addr,
+                len: self.memory.len(),
+            });
+        }
+        self.pc = addr;
+        Ok(())
+    }
+
+    /// Whether the instruction at the pc is an `HLT`,
+    /// meaning running again would only halt again.
+    pub fn is_halted(&self) -> bool {
+        Instruction::decode(self.memory.get(self.pc))
+            .map(|instruction| instruction.opcode == Opcode::HLT)
+            .unwrap_or(false)
+    }
+
+    /// The base that relative-mode parameters are offset from.
+    pub fn rel_base(&self) -> T {
+        self.rel_base
+    }
+
+    /// Read the value at `addr`. Addresses past the end of memory read as 0.
+    pub fn peek(&self, addr: usize) -> T {
+        self.memory.get(addr)
+    }
+
+    /// Write `value` to `addr`, growing memory if needed.
+    pub fn poke(&mut self, addr: usize, value: T) {
+        *self.memory.get_mut(addr) = value;
+    }
+
+    /// Write `data` starting at `addr`, growing memory if needed,
+    /// to overlay another program or block of data.
+    pub fn load_at(&mut self, addr: usize, data: &[T]) {
+        for (offset, &value) in data.iter().enumerate() {
+            *self.memory.get_mut(addr + offset) = value;
+        }
+    }
+
+    /// Check the program before running it, walking memory from address 0
+    /// the same way as `disassemble`.
+    /// Fails on the first cell that doesn't decode, or an instruction
+    /// that would write to an immediate parameter.
+    pub fn validate(&self) -> Result<()> {
+        let mut addr = 0;
+
+        while addr < self.memory.len() {
+            let operation = Operation::from_int(self.memory.get(addr))
+                .map_err(|e| format_err!("Invalid instruction at {}: {}", addr, e))?;
+
+            let write_mode = match operation.opcode {
+                Opcode::ADD | Opcode::MUL | Opcode::LT | Opcode::EQ => Some(operation.mode(3)),
+                Opcode::STR => Some(operation.mode(1)),
+                _ => None,
+            };
+            ensure!(
+                write_mode != Some(Mode::Immediate),
+                "{:?} at {} writes to an immediate",
+                operation.opcode,
+                addr
+            );
+
+            if operation.opcode == Opcode::HLT {
+                break;
+            }
+            addr += operation.opcode.instruction_length();
+        }
+
+        Ok(())
+    }
+
+    /// A summary for a debugger to show: the pc, relative base,
+    /// how many instructions have run, a disassembly of the next `count` instructions,
+    /// and the breakpoints that are set.
+    pub fn state_report(&self, count: usize) -> String {
+        let mut report = format!(
+            "pc {}, rel_base {}, {} instructions executed\n",
+            self.pc,
+            self.rel_base,
+            self.instruction_count()
+        );
+
+        for (_, line) in self.disassemble_from(self.pc, count).0 {
+            report.push_str(&format!("  {}\n", line));
+        }
+
+        let mut breakpoints: Vec<_> = self.breakpoints.iter().collect();
+        breakpoints.sort();
+        report.push_str(&format!("breakpoints: {:?}\n", breakpoints));
+        report
+    }
+
+    /// Memory as a comma-separated program, which `parse_program` can read back,
+    /// to save a machine's state after it's run.
+    pub fn to_program_string(&self) -> String {
+        self.memory
+            .to_vec()
+            .iter()
+            .map(T::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Describe the machine for debugging: the pc and relative base,
+    /// then the memory cells up to `window` addresses either side of the pc,
+    /// with the pc's cell marked and decoded, e.g. `> 0004: 1002 (MUL pos imm pos)`.
+    pub fn dump(&self, window: usize) -> String {
+        let mut dump = format!("pc {}, rel_base {}\n", self.pc, self.rel_base);
+        let end = (self.pc + window + 1).min(self.memory.len());
+
+        for addr in self.pc.saturating_sub(window)..end {
+            let value = self.memory.get(addr);
+            if addr == self.pc {
+                let decoded = match Operation::from_int(value) {
+                    Ok(operation) => operation.to_string(),
+                    Err(_) => "invalid".to_owned(),
+                };
+                dump.push_str(&format!("> {:04}: {} ({})\n", addr, value, decoded));
+            } else {
+                dump.push_str(&format!("  {:04}: {}\n", addr, value));
+            }
+        }
+        dump
+    }
+
+    /// Walk memory from address 0, decoding each instruction into
+    /// lines like `0004: OUT [pos 5]`.
+    /// After the first `HLT`, or from the first cell that doesn't decode,
+    /// every remaining cell is shown as raw `DATA`, so nothing is lost.
+    pub fn disassemble(&self) -> Vec<(usize, String)> {
+        let (mut lines, end) = self.disassemble_from(0, usize::MAX);
+        for addr in end..self.memory.len() {
+            lines.push((addr, format!("{:04}: DATA {}", addr, self.memory.get(addr))));
+        }
+        lines
+    }
+
+    /// Decode instructions starting at `addr`, giving at most `limit` lines,
+    /// and stopping after the first `HLT` or at the first cell that doesn't decode,
+    /// which is shown as raw `DATA`. An instruction cut short by the end of memory
+    /// doesn't decode. Also gives the address after the last line.
+    fn disassemble_from(&self, mut addr: usize, limit: usize) -> (Vec<(usize, String)>, usize) {
+        let mut lines = Vec::new();
+
+        while addr < self.memory.len() && lines.len() < limit {
+            let raw = self.memory.get(addr);
+            let operation = match Operation::from_int(raw) {
+                Ok(operation)
+                    if addr + operation.opcode.instruction_length() <= self.memory.len() =>
+                {
+                    operation
+                }
+                _ => {
+                    lines.push((addr, format!("{:04}: DATA {}", addr, raw)));
+                    addr += 1;
+                    break;
+                }
+            };
+
+            let mut line = format!("{:04}: {:?}", addr, operation.opcode);
+            for (offset, mode) in operation.param_modes().enumerate() {
+                let arg = self.memory.get(addr + offset + 1);
+                line.push_str(&format!(" [{} {}]", mode, arg));
+            }
+            lines.push((addr, line));
+
+            addr += operation.opcode.instruction_length();
+            if operation.opcode == Opcode::HLT {
+                break;
+            }
         }
+
+        (lines, addr)
     }
 
     fn exec_operation(
         &mut self,
-        operation: Operation,
-        input: &mut dyn FnMut() -> Option<isize>,
-    ) -> anyhow::Result<Option<Event>> {
+        instruction: Instruction,
+        input: &mut dyn FnMut() -> Option<T>,
+    ) -> Result<Option<Event<T>>, IntcodeError> {
         use Opcode::*;
-        match operation.opcode {
+        match instruction.opcode {
             ADD => {
-                let augend = self.load_arg(1, operation.mode1)?;
-                let addend = self.load_arg(2, operation.mode2)?;
-                let sum = augend + addend;
-                self.store_arg(3, operation.mode3, sum)?;
+                let augend = self.load_arg(1, instruction.mode(1)?)?;
+                let addend = self.load_arg(2, instruction.mode(2)?)?;
+                let sum = if self.checked {
+                    augend
+                        .checked_add(&addend)
+                        .ok_or_else(|| self.overflow(ADD, augend, addend))?
+                } else {
+                    augend.wrapping_add(&addend)
+                };
+                self.store_arg(3, instruction.mode(3)?, sum)?;
             }
             MUL => {
-                let multiplicand = self.load_arg(1, operation.mode1)?;
-                let multiplier = self.load_arg(2, operation.mode2)?;
-                let product = multiplicand * multiplier;
-                self.store_arg(3, operation.mode3, product)?;
+                let multiplicand = self.load_arg(1, instruction.mode(1)?)?;
+                let multiplier = self.load_arg(2, instruction.mode(2)?)?;
+                let product = if self.checked {
+                    multiplicand
+                        .checked_mul(&multiplier)
+                        .ok_or_else(|| self.overflow(MUL, multiplicand, multiplier))?
+                } else {
+                    multiplicand.wrapping_mul(&multiplier)
+                };
+                self.store_arg(3, instruction.mode(3)?, product)?;
             }
             LT => {
-                let left = self.load_arg(1, operation.mode1)?;
-                let right = self.load_arg(2, operation.mode2)?;
+                let left = self.load_arg(1, instruction.mode(1)?)?;
+                let right = self.load_arg(2, instruction.mode(2)?)?;
                 let result = left < right;
-                let result = if result { 1 } else { 0 };
-                self.store_arg(3, operation.mode3, result)?;
+                let result = if result { T::one() } else { T::zero() };
+                self.store_arg(3, instruction.mode(3)?, result)?;
             }
             EQ => {
-                let left = self.load_arg(1, operation.mode1)?;
-                let right = self.load_arg(2, operation.mode2)?;
+                let left = self.load_arg(1, instruction.mode(1)?)?;
+                let right = self.load_arg(2, instruction.mode(2)?)?;
                 let result = left == right;
-                let result = if result { 1 } else { 0 };
-                self.store_arg(3, operation.mode3, result)?;
+                let result = if result { T::one() } else { T::zero() };
+                self.store_arg(3, instruction.mode(3)?, result)?;
             }
             JIT => {
-                let test = self.load_arg(1, operation.mode1)?;
-                let addr = self.load_arg(2, operation.mode2)?;
-                let addr = convert_addr(addr)?;
-                if test != 0 {
+                let test = self.load_arg(1, instruction.mode(1)?)?;
+                let addr = self.load_arg(2, instruction.mode(2)?)?;
+                let addr = self.convert_addr(addr, 2)?;
+                if !test.is_zero() {
                     self.pc = addr;
                 } else {
-                    self.pc += operation.opcode.instruction_length();
+                    self.pc += instruction.length;
                 }
             }
             JIF => {
-                let test = self.load_arg(1, operation.mode1)?;
-                let addr = self.load_arg(2, operation.mode2)?;
-                let addr = convert_addr(addr)?;
-                if test == 0 {
+                let test = self.load_arg(1, instruction.mode(1)?)?;
+                let addr = self.load_arg(2, instruction.mode(2)?)?;
+                let addr = self.convert_addr(addr, 2)?;
+                if test.is_zero() {
                     self.pc = addr;
                 } else {
-                    self.pc += operation.opcode.instruction_length();
+                    self.pc += instruction.length;
                 }
             }
             STR => {
                 if let Some(input) = (input)() {
-                    self.store_arg(1, operation.mode1, input)?;
+                    self.store_arg(1, instruction.mode(1)?, input)?;
                 } else {
                     return Ok(Some(Event::RequestingInput));
                 }
             }
             OUT => {
-                let output = self.load_arg(1, operation.mode1)?;
-                self.pc += operation.opcode.should_move();
+                let output = self.load_arg(1, instruction.mode(1)?)?;
+                self.pc += instruction.should_move;
                 return Ok(Some(Event::HaveOutput(output)));
             }
             BAS => {
-                let augend = self.load_arg(1, operation.mode1)?;
-                self.rel_base += augend;
+                // even unchecked, a wrapped base couldn't point anywhere useful
+                let augend = self.load_arg(1, instruction.mode(1)?)?;
+                let old = self.rel_base;
+                self.rel_base = old
+                    .checked_add(&augend)
+                    .ok_or_else(|| self.overflow(BAS, old, augend))?;
+                if let Some(history) = &mut self.rel_base_history {
+                    history.push((self.pc, old, self.rel_base));
+                }
             }
             HLT => return Ok(Some(Event::Halted)),
             // _ => unimplemented!(),
         }
-        self.pc += operation.opcode.should_move();
+        self.pc += instruction.should_move;
 
         Ok(None)
     }
 
-    fn exec_current(&mut self, input: &mut dyn FnMut() -> Option<isize>) -> Result<Option<Event>> {
-        let operation = self.decode()?;
-        self.exec_operation(operation, input)
+    fn overflow(&self, opcode: Opcode, left: T, right: T) -> IntcodeError {
+        IntcodeError::Overflow {
+            opcode,
+            pc: self.pc,
+            left: wide(left),
+            right: wide(right),
+        }
     }
 
-    pub fn execute(&mut self, input: &mut dyn FnMut() -> Option<isize>) -> Result<Event> {
-        use Event::*;
-        let mut result = self.exec_current(input)?;
-        loop {
-            match result {
-                None => {
-                    result = self.exec_current(input)?;
-                }
-                Some(RequestingInput) => break Ok(RequestingInput),
-                Some(x) => break Ok(x),
-            }
+    /// Execute the instruction, counting it unless it had to wait for input.
+    fn exec_counted(
+        &mut self,
+        instruction: Instruction,
+        input: &mut dyn FnMut() -> Option<T>,
+    ) -> Result<Option<Event<T>>, IntcodeError> {
+        let opcode = instruction.opcode.clone();
+        let event = self.exec_operation(instruction, input)?;
+        if event != Some(Event::RequestingInput) {
+            self.instructions_executed += 1;
+            self.cycle_count += self
+                .cycle_costs
+                .get(&opcode)
+                .copied()
+                .unwrap_or_else(|| opcode.default_cost());
+            *self.opcode_counts.entry(opcode).or_insert(0) += 1;
+            self.stopped_at = None;
+        }
+        match self.self_modified.take() {
+            Some((addr, pc)) => Ok(Some(Event::SelfModified { addr, pc })),
+            None => Ok(event),
         }
     }
-}
 
-pub fn stdin_to_prog() -> anyhow::Result<Vec<isize>> {
-    let stdin = io::stdin();
-    let stdin = stdin.lock();
-
-    stdin
-        .split(b',')
-        .filter(|maybe_bytes| match maybe_bytes {
-            Ok(ref bytes) => bytes.len() > 0 && bytes[0] != b'\n',
-            _ => true,
-        })
-        .map(|maybe_bytes| -> anyhow::Result<isize> {
-            let bytes = maybe_bytes?;
-            let string = std::str::from_utf8(&bytes)?;
-            let num = isize::from_str(string.trim())?;
-            Ok(num)
-        })
-        .collect()
-}
+    /// Stop if the pc is at a breakpoint we haven't already stopped at.
+    fn check_breakpoint(&mut self) -> Option<Event<T>> {
+        if self.breakpoints.contains(&self.pc) && self.stopped_at != Some(self.pc) {
+            self.stopped_at = Some(self.pc);
+            Some(Event::Breakpoint(self.pc))
+        } else {
+            None
+        }
+    }
 
-pub fn first_arg_to_prog() -> anyhow::Result<Vec<isize>> {
-    // println!("{}", std::env::current_dir()?.display());
-    let string = read_to_string("input")?;
-    string
-        .split(',')
-        .map(|string| -> anyhow::Result<isize> {
-            let num = isize::from_str(string.trim())?;
-            Ok(num)
-        })
-        .collect()
-}
+    /// Execute exactly one instruction.
+    /// Returns `None` if the machine should keep going,
+    /// or the event it stopped for: output, waiting for input, a breakpoint, or halting.
+    pub fn step(
+        &mut self,
+        input: &mut dyn FnMut() -> Option<T>,
+    ) -> Result<Option<Event<T>>, IntcodeError> {
+        if let Some(event) = self.check_breakpoint() {
+            return Ok(Some(event));
+        }
+        let instruction = self.decode()?;
+        self.exec_counted(instruction, input)
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::{Event, IntcodeComputer};
-    use Event::*;
+    /// Make `execute` return `Event::Breakpoint` when the pc reaches `addr`,
+    /// before the instruction there runs. Executing again resumes from it.
+    pub fn add_breakpoint(&mut self, addr: usize) {
+        self.breakpoints.insert(addr);
+    }
 
-    macro_rules! tests {
-        ($($name:ident {
-            prog: $prog:expr,
-            final: $final:expr,
-        });*) => {
-            $(
-                #[test]
-                fn $name() {
-                    let program = $prog;
+    pub fn remove_breakpoint(&mut self, addr: usize) {
+        self.breakpoints.remove(&addr);
+    }
 
-                    let mut cpu = IntcodeComputer::new(program);
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
 
-                    assert_eq!(cpu.execute(None).unwrap(), Event::Halted);
+    /// Call `callback` with the old and new value every time the program writes to `addr`,
+    /// even if the value doesn't change. Writes made with `poke` aren't reported.
+    /// An address can have any number of watchers.
+    pub fn watch(&mut self, addr: usize, callback: impl FnMut(T, T) + 'static) {
+        self.watchers
+            .entry(addr)
+            .or_default()
+            .push(Box::new(callback));
+    }
 
-                    assert_eq!(cpu.memory, $final);
-                }
-            )*
-        };
+    /// How many instructions have been executed so far.
+    /// An input instruction is only counted once it actually gets its input.
+    pub fn instruction_count(&self) -> u64 {
+        self.instructions_executed
     }
 
-    macro_rules! iotests {
-        ($($name:ident {
-            prog: $prog:expr,
-            final: $final:expr,
-            input: $input:expr,
-            output: $output:expr,
-        };)*) => {
-            $(
-                #[test]
-                fn $name() {
-                    let program = $prog;
+    /// The address parameter `param` (counting from 1) of `operation` would read or write
+    /// if `operation` ran at the current pc, or `None` if it's an immediate.
+    pub fn effective_addr(
+        &self,
+        operation: &Operation,
+        param: usize,
+    ) -> Result<Option<usize>, IntcodeError> {
+        self.resolve_addr(param, operation.mode(param))
+    }
 
-                    let mut cpu = IntcodeComputer::new(program);
+    /// The highest address any instruction has read or written through a parameter,
+    /// for seeing how much memory a program really needs.
+    /// Reading the instructions themselves doesn't count.
+    pub fn max_addr_used(&self) -> usize {
+        self.high_water_mark
+    }
 
-                    let mut input = None;
+    /// `(pc, old, new)` for every `BAS` executed so far,
+    /// if `IntcodeComputerBuilder::record_rel_base` turned recording on. Empty otherwise.
+    pub fn rel_base_history(&self) -> &[(usize, T, T)] {
+        self.rel_base_history.as_deref().unwrap_or(&[])
+    }
 
-                    loop {
-                        let res = cpu.execute(input).unwrap();
-                        input = None;
-                        match res {
-                            Event::Halted => break,
-                            Event::RequestingInput => { input = Some(($input)()); },
-                            Event::HaveOutput(x) => { ($output)(x); }
-                        }
-                    }
+    /// The total cost of the instructions executed so far, going by `Opcode::default_cost`
+    /// or the costs given to `IntcodeComputerBuilder::cycle_costs`.
+    pub fn cycles(&self) -> u64 {
+        self.cycle_count
+    }
 
-                    assert_eq!(cpu.memory, $final);
-                }
-            )*
-        };
+    /// How many times each opcode has been executed so far,
+    /// for finding out where a program spends its time.
+    /// Opcodes that haven't run are absent.
+    pub fn opcode_counts(&self) -> &HashMap<Opcode, u64> {
+        &self.opcode_counts
     }
 
-    tests! {
-        day02_main_example {
-            prog: vec![1,9,10,3,2,3,11,0,99,30,40,50],
-            final: vec![3500,9,10,70, 2,3,11,0,99,30,40,50],
-        };
-        day02_smol_1 {
-            prog: vec![1,0,0,0,99],
-            final: vec![2,0,0,0,99],
-        };
-        day02_smol_2 {
-            prog: vec![2,3,0,3,99],
-            final: vec![2,3,0,6,99],
-        };
-        day02_smol_3 {
-            prog: vec![2,4,4,5,99,0],
-            final: vec![2,4,4,5,99,9801],
-        };
-        day02_smol_4 {
-            prog: vec![1,1,1,4,99,5,6,0,99],
-            final: vec![30,1,1,4,2,5,6,0,99],
+    /// Run until the next event. This is just `step` in a loop.
+    pub fn execute(
+        &mut self,
+        input: &mut dyn FnMut() -> Option<T>,
+    ) -> Result<Event<T>, IntcodeError> {
+        loop {
+            if let Some(event) = self.step(input)? {
+                break Ok(event);
+            }
+        }
+    }
+
+    /// Like `execute`, but reading input from `inputs`,
+    /// stopping with `Event::RequestingInput` once it runs out.
+    pub fn execute_from_iter<I: Iterator<Item = T>>(
+        &mut self,
+        inputs: &mut I,
+    ) -> Result<Event<T>, IntcodeError> {
+        self.execute(&mut || inputs.next())
+    }
+
+    /// Like `execute`, but giving `input` the first time the program reads, if it's given,
+    /// instead of calling a closure. Without one, a read stops with `Event::RequestingInput`.
+    pub fn resume(&mut self, mut input: Option<T>) -> Result<Event<T>, IntcodeError> {
+        self.execute(&mut || input.take())
+    }
+
+    /// Like `execute`, but when `input` runs dry, `policy` decides what happens
+    /// instead of returning `Event::RequestingInput`.
+    pub fn execute_with_policy(
+        &mut self,
+        input: &mut dyn FnMut() -> Option<T>,
+        policy: InputPolicy,
+    ) -> Result<Event<T>, IntcodeError> {
+        if policy == InputPolicy::ReturnZero {
+            return self.execute(&mut || Some(input().unwrap_or_else(T::zero)));
+        }
+
+        match self.execute(input)? {
+            Event::RequestingInput if policy == InputPolicy::Error => {
+                Err(IntcodeError::InputExhausted { pc: self.pc })
+            }
+            Event::RequestingInput => Ok(Event::Halted),
+            event => Ok(event),
+        }
+    }
+
+    /// Like `execute`, but fails if the program runs `max_steps` instructions
+    /// without stopping, so a program stuck in a loop can't hang the caller.
+    pub fn execute_bounded(
+        &mut self,
+        input: &mut dyn FnMut() -> Option<T>,
+        max_steps: u64,
+    ) -> Result<Event<T>, IntcodeError> {
+        for _ in 0..max_steps {
+            if let Some(event) = self.step(input)? {
+                return Ok(event);
+            }
+        }
+        Err(IntcodeError::StepLimit {
+            limit: max_steps,
+            pc: self.pc,
+        })
+    }
+
+    /// Like `execute`, but before each instruction writes a line to `trace` with
+    /// the pc, the decoded operation, the values its parameters read
+    /// and the address it writes to, if any, and the relative base,
+    /// e.g. `0002: EQ pos imm pos [8, 8, @9] rel_base=0`.
+    /// Tracing only looks at memory, so it doesn't change `max_addr_used`.
+    /// An input instruction that has to wait for input is traced again when resumed.
+    pub fn execute_traced<W: Write>(
+        &mut self,
+        input: &mut dyn FnMut() -> Option<T>,
+        trace: &mut W,
+    ) -> Result<Event<T>> {
+        loop {
+            if let Some(event) = self.check_breakpoint() {
+                break Ok(event);
+            }
+            let instruction = self.decode()?;
+            self.write_trace(&instruction.to_operation()?, trace)?;
+            if let Some(event) = self.exec_counted(instruction, input)? {
+                break Ok(event);
+            }
+        }
+    }
+
+    fn write_trace(&self, operation: &Operation, trace: &mut impl Write) -> Result<()> {
+        let store_param = operation.opcode.store_param();
+        let args: Vec<String> = operation
+            .param_modes()
+            .enumerate()
+            .map(|(idx, &mode)| {
+                let offset = idx + 1;
+                let arg = if store_param == Some(offset) {
+                    self.resolve_addr(offset, mode)
+                        .map(|addr| addr.map_or("?".to_owned(), |addr| format!("@{}", addr)))
+                } else {
+                    self.peek_arg(offset, mode).map(|value| value.to_string())
+                };
+                arg.unwrap_or_else(|_| "?".to_owned())
+            })
+            .collect();
+
+        writeln!(
+            trace,
+            "{:04}: {} [{}] rel_base={}",
+            self.pc,
+            operation,
+            args.join(", "),
+            self.rel_base
+        )?;
+        Ok(())
+    }
+
+    /// Run until halted using the closures given to `new_with_io` or `new_with_writer`.
+    pub fn execute_io(&mut self) -> Result<()> {
+        let mut io = self
+            .io
+            .take()
+            .ok_or_else(|| format_err!("No I/O closures; construct with new_with_io"))?;
+
+        let result = loop {
+            let input = &mut io.input;
+            match self.execute(&mut || Some(input())) {
+                Ok(Event::HaveOutput(x)) => {
+                    if let Err(e) = (io.output)(x) {
+                        break Err(e.into());
+                    }
+                }
+                Ok(Event::Breakpoint(_)) | Ok(Event::SelfModified { .. }) => continue,
+                Ok(Event::Halted) => break Ok(()),
+                Ok(event) => break Err(format_err!("Unexpected event {:?}", event)),
+                Err(e) => break Err(e.into()),
+            }
+        };
+
+        self.io = Some(io);
+        result
+    }
+
+    /// Run until halted, failing if the program asks for input.
+    fn run_to_halt(&mut self) -> Result<()> {
+        loop {
+            match self.execute(&mut || None)? {
+                Event::HaveOutput(_) | Event::Breakpoint(_) | Event::SelfModified { .. } => {
+                    continue
+                }
+                Event::Halted => break Ok(()),
+                Event::RequestingInput => break Err(format_err!("Program requested input")),
+            }
+        }
+    }
+
+    /// Run until halted with no input, giving what's left at address 0,
+    /// which is where day 2's programs leave their result.
+    /// Fails if the program asks for input.
+    pub fn run_for_result(&mut self) -> Result<T> {
+        self.run_to_halt()?;
+        Ok(self.peek(0))
+    }
+
+    /// Run until halted, feeding `inputs` in order and collecting every output.
+    /// Fails if the program asks for more input than was supplied.
+    pub fn run_collect(&mut self, inputs: impl IntoIterator<Item = T>) -> Result<Vec<T>> {
+        let mut inputs = inputs.into_iter();
+        let mut outputs = Vec::new();
+        loop {
+            match self.execute(&mut || inputs.next())? {
+                Event::HaveOutput(x) => outputs.push(x),
+                Event::Breakpoint(_) | Event::SelfModified { .. } => continue,
+                Event::Halted => break Ok(outputs),
+                Event::RequestingInput => {
+                    break Err(format_err!(
+                        "Program requested more input than was supplied"
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Like `run_collect`, but also reports how many instructions the run took.
+    pub fn execute_until_halt(
+        &mut self,
+        inputs: impl IntoIterator<Item = T>,
+    ) -> Result<RunSummary<T>> {
+        let start = self.instructions_executed;
+        let outputs = self.run_collect(inputs)?;
+        Ok(RunSummary {
+            outputs,
+            instructions: self.instructions_executed - start,
+        })
+    }
+
+    /// Queue up a line of text as ASCII input, followed by a newline.
+    pub fn feed_line(&mut self, line: &str) {
+        self.push_inputs(line.bytes().map(Into::into));
+        self.push_input(b'\n'.into());
+    }
+
+    /// Run until the program has output `n` more values and return them,
+    /// for programs that output in groups, like `(x, y, tile)`.
+    /// Gives `None` if the program halts before starting a group,
+    /// and fails if it halts partway through one or `input` runs dry.
+    pub fn next_n_outputs(
+        &mut self,
+        n: usize,
+        input: &mut dyn FnMut() -> Option<T>,
+    ) -> Result<Option<Vec<T>>> {
+        let mut outputs = Vec::with_capacity(n);
+        while outputs.len() < n {
+            match self.execute(input)? {
+                Event::HaveOutput(x) => outputs.push(x),
+                Event::Breakpoint(_) | Event::SelfModified { .. } => continue,
+                Event::Halted if outputs.is_empty() => return Ok(None),
+                Event::Halted => {
+                    return Err(format_err!(
+                        "Halted after {} of a group of {} outputs",
+                        outputs.len(),
+                        n
+                    ))
+                }
+                Event::RequestingInput => {
+                    return Err(format_err!("Program requested input at pc {}", self.pc))
+                }
+            }
+        }
+        Ok(Some(outputs))
+    }
+
+    /// Run until halted, handing outputs to `on_batch` `batch` at a time
+    /// instead of one by one. Whatever's left over is flushed when the program halts.
+    /// Fails if `input` runs dry, after flushing what's been output so far.
+    pub fn execute_batched(
+        &mut self,
+        input: &mut dyn FnMut() -> Option<T>,
+        batch: usize,
+        mut on_batch: impl FnMut(&[T]),
+    ) -> Result<()> {
+        let batch = batch.max(1);
+        let mut buffer = Vec::with_capacity(batch);
+        loop {
+            match self.execute(input)? {
+                Event::HaveOutput(x) => {
+                    buffer.push(x);
+                    if buffer.len() == batch {
+                        on_batch(&buffer);
+                        buffer.clear();
+                    }
+                }
+                Event::Breakpoint(_) | Event::SelfModified { .. } => continue,
+                event => {
+                    if !buffer.is_empty() {
+                        on_batch(&buffer);
+                    }
+                    return match event {
+                        Event::Halted => Ok(()),
+                        _ => Err(format_err!("Program requested input at pc {}", self.pc)),
+                    };
+                }
+            }
+        }
+    }
+
+    /// Like `run_collect`, but reading from the input queue.
+    fn run_collect_queued(&mut self) -> Result<Vec<T>> {
+        let mut outputs = Vec::new();
+        loop {
+            match self.execute_buffered()? {
+                Event::HaveOutput(x) => outputs.push(x),
+                Event::Breakpoint(_) | Event::SelfModified { .. } => continue,
+                Event::Halted => break Ok(outputs),
+                Event::RequestingInput => {
+                    break Err(format_err!("Program requested more input than was queued"))
+                }
+            }
+        }
+    }
+
+    /// Run from the input queue until halted or out of input,
+    /// collecting ASCII outputs as text.
+    /// Outputs outside the ASCII range (like a final answer) are returned separately.
+    pub fn run_ascii(&mut self) -> Result<(String, Vec<T>)> {
+        let mut text = String::new();
+        let mut values = Vec::new();
+        loop {
+            match self.execute_buffered()? {
+                Event::HaveOutput(x) => match x.to_u8() {
+                    Some(byte) if byte.is_ascii() => text.push(byte as char),
+                    _ => values.push(x),
+                },
+                Event::Breakpoint(_) | Event::SelfModified { .. } => continue,
+                Event::Halted | Event::RequestingInput => break Ok((text, values)),
+            }
+        }
+    }
+
+    /// Iterate over the program's outputs, stopping once it halts.
+    /// If the program asks for input that `input` can't give,
+    /// the iterator yields one error and then stops.
+    pub fn outputs<'a>(&'a mut self, input: impl FnMut() -> Option<T> + 'a) -> Outputs<'a, T> {
+        Outputs {
+            cpu: self,
+            input: Box::new(input),
+            done: false,
+        }
+    }
+
+    /// Queue up a value to be read by `execute_buffered`.
+    pub fn push_input(&mut self, value: T) {
+        self.inputs.push_back(value);
+    }
+
+    /// Queue up several values to be read by `execute_buffered`, in order.
+    pub fn push_inputs(&mut self, values: impl IntoIterator<Item = T>) {
+        self.inputs.extend(values);
+    }
+
+    /// Like `execute`, but reads input from the queue filled by `push_input`.
+    /// Returns `RequestingInput` once the queue is empty.
+    pub fn execute_buffered(&mut self) -> Result<Event<T>, IntcodeError> {
+        let mut inputs = std::mem::take(&mut self.inputs);
+        let result = self.execute(&mut || inputs.pop_front());
+        self.inputs = inputs;
+        result
+    }
+}
+
+impl<T: Int> From<Vec<T>> for IntcodeComputer<T> {
+    fn from(program: Vec<T>) -> IntcodeComputer<T> {
+        IntcodeComputer::new(program)
+    }
+}
+
+/// Copies the program, for when it's borrowed, like a `const` array.
+impl<T: Int> From<&[T]> for IntcodeComputer<T> {
+    fn from(program: &[T]) -> IntcodeComputer<T> {
+        IntcodeComputer::from(program.to_vec())
+    }
+}
+
+/// Shows `dump` with a window of 8 cells.
+impl<T: Int> fmt::Debug for IntcodeComputer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.dump(8))
+    }
+}
+
+/// An iterator over a running computer's outputs, made by `IntcodeComputer::outputs`.
+pub struct Outputs<'a, T = isize> {
+    cpu: &'a mut IntcodeComputer<T>,
+    input: Box<dyn FnMut() -> Option<T> + 'a>,
+    done: bool,
+}
+
+impl<T: Int> Iterator for Outputs<'_, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = loop {
+            match self.cpu.execute(&mut *self.input) {
+                Ok(Event::HaveOutput(x)) => return Some(Ok(x)),
+                Ok(Event::Breakpoint(_)) | Ok(Event::SelfModified { .. }) => continue,
+                Ok(Event::Halted) => break None,
+                Ok(Event::RequestingInput) => {
+                    break Some(Err(format_err!("Program requested input")))
+                }
+                Err(e) => break Some(Err(e.into())),
+            }
+        };
+        self.done = true;
+        result
+    }
+}
+
+/// Run `program` until it halts, feeding it `inputs` in order,
+/// and return everything it output.
+/// Fails if it asks for more input than was given.
+///
+/// ```
+/// // multiply two inputs
+/// let program = vec![3, 11, 3, 12, 2, 11, 12, 11, 4, 11, 99, 0, 0];
+/// assert_eq!(intcode::run(program, vec![6, 7]).unwrap(), vec![42]);
+/// ```
+///
+/// ```
+/// // echo one input back
+/// assert_eq!(intcode::run(vec![3, 0, 4, 0, 99], vec![5]).unwrap(), vec![5]);
+/// assert!(intcode::run(vec![3, 0, 4, 0, 99], vec![]).is_err());
+/// ```
+pub fn run<T: Int>(program: Vec<T>, inputs: Vec<T>) -> Result<Vec<T>> {
+    IntcodeComputer::new(program).run_collect(inputs)
+}
+
+/// Run `first` until halted, reading from its input queue,
+/// then queue all its outputs for `second` and run that until halted, returning its outputs.
+/// Fails if either asks for more input than it has queued.
+pub fn pipe<T: Int>(
+    first: &mut IntcodeComputer<T>,
+    second: &mut IntcodeComputer<T>,
+) -> Result<Vec<T>> {
+    let outputs = first.run_collect_queued()?;
+    second.push_inputs(outputs);
+    second.run_collect_queued()
+}
+
+/// Whether `program` outputs a copy of itself when run with no input.
+/// Fails if it asks for input.
+pub fn is_quine(program: &[isize]) -> Result<bool> {
+    Ok(run(program.to_vec(), Vec::new())? == program)
+}
+
+/// Everything `program` outputs given `inputs`, and how it stopped:
+/// halting, asking for more input, or the error it failed with.
+fn record_run(program: Vec<isize>, inputs: &[isize]) -> (Vec<isize>, String) {
+    let mut cpu = IntcodeComputer::new(program);
+    let mut inputs = inputs.iter().copied();
+    let mut outputs = Vec::new();
+    loop {
+        match cpu.execute(&mut || inputs.next()) {
+            Ok(Event::HaveOutput(x)) => outputs.push(x),
+            Ok(Event::Breakpoint(_)) | Ok(Event::SelfModified { .. }) => continue,
+            Ok(Event::Halted) => break (outputs, "halted".to_owned()),
+            Ok(Event::RequestingInput) => break (outputs, "ran out of input".to_owned()),
+            Err(e) => break (outputs, format!("failed with \"{}\"", e)),
+        }
+    }
+}
+
+/// Run `a` and `b` with the same `inputs`, for checking one intcode implementation
+/// or program against another. Fails at the first output that differs,
+/// or if they output the same but stop differently.
+pub fn compare_runs(a: Vec<isize>, b: Vec<isize>, inputs: Vec<isize>) -> Result<()> {
+    let (a_outputs, a_end) = record_run(a, &inputs);
+    let (b_outputs, b_end) = record_run(b, &inputs);
+
+    let described = |outputs: &[isize], idx: usize| match outputs.get(idx) {
+        Some(value) => value.to_string(),
+        None => "nothing".to_owned(),
+    };
+    for idx in 0..a_outputs.len().max(b_outputs.len()) {
+        ensure!(
+            a_outputs.get(idx) == b_outputs.get(idx),
+            "Output {} differs: {} vs {}",
+            idx,
+            described(&a_outputs, idx),
+            described(&b_outputs, idx)
+        );
+    }
+    ensure!(
+        a_end == b_end,
+        "Both output the same, but the first {} and the second {}",
+        a_end,
+        b_end
+    );
+    Ok(())
+}
+
+/// `program`'s disassembly as text, one instruction per line, which `assemble` can read back.
+pub fn disassemble_string(program: &[isize]) -> String {
+    IntcodeComputer::from(program)
+        .disassemble()
+        .into_iter()
+        .map(|(_, line)| line + "\n")
+        .collect()
+}
+
+/// Every cell that differs between two snapshots of memory, as `(addr, old, new)`.
+/// The shorter one is treated as if padded with zeroes, since memory grows.
+pub fn diff_memory<T: Int>(before: &[T], after: &[T]) -> Vec<(usize, T, T)> {
+    let cell = |memory: &[T], addr| memory.get(addr).copied().unwrap_or_else(T::zero);
+    (0..before.len().max(after.len()))
+        .map(|addr| (addr, cell(before, addr), cell(after, addr)))
+        .filter(|(_, old, new)| old != new)
+        .collect()
+}
+
+/// Try every pair of values in `range` at `noun_addr` and `verb_addr`,
+/// returning the first pair that leaves `target` at `result_addr` once halted.
+/// Candidates that error or ask for input are skipped.
+pub fn search_inputs(
+    program: &[isize],
+    noun_addr: usize,
+    verb_addr: usize,
+    result_addr: usize,
+    target: isize,
+    range: RangeInclusive<isize>,
+) -> Option<(isize, isize)> {
+    for noun in range.clone() {
+        for verb in range.clone() {
+            let mut cpu = IntcodeComputer::from(program);
+            cpu.poke(noun_addr, noun);
+            cpu.poke(verb_addr, verb);
+
+            match cpu.run_to_halt() {
+                Ok(()) if cpu.peek(result_addr) == target => return Some((noun, verb)),
+                _ => continue,
+            }
+        }
+    }
+    None
+}
+
+/// Starts every program written by `to_bytes`.
+const BYTES_MAGIC: &[u8; 4] = b"INTC";
+/// The format `to_bytes` writes, bumped if it ever changes.
+const BYTES_VERSION: u8 = 1;
+
+/// Encode a program compactly for caching on disk:
+/// a magic header and version byte, then each cell as a little-endian `i64`.
+pub fn to_bytes(memory: &[isize]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(BYTES_MAGIC.len() + 1 + memory.len() * 8);
+    bytes.extend_from_slice(BYTES_MAGIC);
+    bytes.push(BYTES_VERSION);
+    for &cell in memory {
+        bytes.extend_from_slice(&(cell as i64).to_le_bytes());
+    }
+    bytes
+}
+
+/// Decode a program written by `to_bytes`.
+/// Fails if the header or version is wrong, or the cells don't fit.
+pub fn from_bytes(bytes: &[u8]) -> Result<Vec<isize>> {
+    let header_len = BYTES_MAGIC.len() + 1;
+    ensure!(
+        bytes.len() >= header_len && bytes.starts_with(BYTES_MAGIC),
+        "Not an intcode program"
+    );
+    let version = bytes[BYTES_MAGIC.len()];
+    ensure!(
+        version == BYTES_VERSION,
+        "Unsupported program version {}",
+        version
+    );
+
+    let cells = bytes[header_len..].chunks_exact(8);
+    ensure!(
+        cells.remainder().is_empty(),
+        "Program has {} bytes left over after its last cell",
+        cells.remainder().len()
+    );
+    cells
+        .map(|chunk| {
+            let mut cell = [0; 8];
+            cell.copy_from_slice(chunk);
+            Ok(isize::try_from(i64::from_le_bytes(cell))?)
+        })
+        .collect()
+}
+
+/// Parse a comma-separated program, ignoring surrounding whitespace
+/// and empty fields (such as after a trailing comma).
+pub fn parse_program(string: &str) -> anyhow::Result<Vec<isize>> {
+    string
+        .split(',')
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            isize::from_str(token).map_err(|e| format_err!("Invalid number {:?}: {}", token, e))
+        })
+        .collect()
+}
+
+/// Read all of `reader` and parse it as a comma-separated program.
+pub fn read_program(mut reader: impl Read) -> anyhow::Result<Vec<isize>> {
+    let mut string = String::new();
+    reader.read_to_string(&mut string)?;
+    parse_program(&string)
+}
+
+pub fn stdin_to_prog() -> anyhow::Result<Vec<isize>> {
+    read_program(io::stdin())
+}
+
+/// Input for `execute` that reads one integer per line from `reader` as the program asks.
+/// Gives `None` at the end of input, and also for a blank or unparseable line,
+/// after logging it to stderr.
+pub fn lines_input(mut reader: impl BufRead) -> impl FnMut() -> Option<isize> {
+    move || {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => isize::from_str(line.trim())
+                .map_err(|e| eprintln!("Invalid input {:?}: {}", line.trim(), e))
+                .ok(),
+            Err(e) => {
+                eprintln!("Couldn't read input: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// `lines_input` from stdin, so a program can be driven from a terminal.
+pub fn stdin_input() -> impl FnMut() -> Option<isize> {
+    lines_input(BufReader::new(io::stdin()))
+}
+
+/// Input for `execute` with programs that never read any, as `&mut no_input`.
+pub fn no_input() -> Option<isize> {
+    None
+}
+
+/// Input for `execute` that gives `value` the first time it's asked, then `None`.
+pub fn once(value: isize) -> impl FnMut() -> Option<isize> {
+    let mut value = Some(value);
+    move || value.take()
+}
+
+/// Input for `execute` that gives the values of `log` in order, then `None`,
+/// to replay a captured run.
+pub fn replay_input(log: Vec<isize>) -> impl FnMut() -> Option<isize> {
+    let mut log = log.into_iter();
+    move || log.next()
+}
+
+/// An output closure for `new_with_io` that records every value,
+/// along with a handle for reading what it's recorded.
+pub fn recording_output() -> (impl FnMut(isize), Rc<RefCell<Vec<isize>>>) {
+    let recorded = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&recorded);
+    (move |value| sink.borrow_mut().push(value), recorded)
+}
+
+/// Wrap an input and output closure so every value that passes through
+/// is also written to `log`, as `> 5` for inputs and `< 999` for outputs.
+/// Failing to write to the log doesn't stop the program.
+pub fn tee_io<I, O>(
+    mut input: I,
+    mut output: O,
+    log: impl Write,
+) -> (impl FnMut() -> Option<isize>, impl FnMut(isize))
+where
+    I: FnMut() -> Option<isize>,
+    O: FnMut(isize),
+{
+    let log = Rc::new(RefCell::new(log));
+    let output_log = Rc::clone(&log);
+
+    let input = move || {
+        let value = input();
+        if let Some(value) = value {
+            let _ = writeln!(log.borrow_mut(), "> {}", value);
+        }
+        value
+    };
+    let output = move |value| {
+        let _ = writeln!(output_log.borrow_mut(), "< {}", value);
+        output(value);
+    };
+    (input, output)
+}
+
+/// Parse a comma-separated program from the file at `path`.
+pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Vec<isize>> {
+    parse_program(&read_to_string(path)?)
+}
+
+/// Load the program from the file named by the first argument,
+/// or `input` if there isn't one.
+pub fn first_arg_to_prog() -> anyhow::Result<Vec<isize>> {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "input".to_owned());
+    from_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, IntcodeComputer};
+    use Event::*;
+
+    macro_rules! tests {
+        ($($name:ident {
+            prog: $prog:expr,
+            final: $final:expr,
+        });*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let program = $prog;
+
+                    let mut cpu = IntcodeComputer::new(program);
+
+                    assert_eq!(cpu.execute(&mut || None).unwrap(), Event::Halted);
+
+                    assert_eq!(cpu.memory.to_vec(), $final, "\n{:?}", cpu);
+                }
+            )*
+        };
+    }
+
+    macro_rules! iotests {
+        ($($name:ident {
+            prog: $prog:expr,
+            final: $final:expr,
+            input: $input:expr,
+            output: $output:expr,
+        };)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let program = $prog;
+
+                    let mut cpu = IntcodeComputer::new(program);
+
+                    let mut input = || Some(($input)());
+
+                    loop {
+                        match cpu.execute(&mut input).unwrap() {
+                            Event::Halted => break,
+                            Event::RequestingInput => panic!("input closure ran dry"),
+                            Event::Breakpoint(_) => panic!("no breakpoints were set"),
+                            Event::SelfModified { .. } => panic!("detection wasn't on"),
+                            Event::HaveOutput(x) => { ($output)(x); }
+                        }
+                    }
+
+                    assert_eq!(cpu.memory.to_vec(), $final, "\n{:?}", cpu);
+                }
+            )*
+        };
+    }
+
+    tests! {
+        day02_main_example {
+            prog: vec![1,9,10,3,2,3,11,0,99,30,40,50],
+            final: vec![3500,9,10,70, 2,3,11,0,99,30,40,50],
+        };
+        day02_smol_1 {
+            prog: vec![1,0,0,0,99],
+            final: vec![2,0,0,0,99],
+        };
+        day02_smol_2 {
+            prog: vec![2,3,0,3,99],
+            final: vec![2,3,0,6,99],
+        };
+        day02_smol_3 {
+            prog: vec![2,4,4,5,99,0],
+            final: vec![2,4,4,5,99,9801],
+        };
+        day02_smol_4 {
+            prog: vec![1,1,1,4,99,5,6,0,99],
+            final: vec![30,1,1,4,2,5,6,0,99],
         };
         day05_main_example {
             prog: vec![1002,4,3,4,33],
@@ -441,8 +1832,8 @@ mod tests {
     }
 
     fn io_halt(mut cpu: IntcodeComputer, pairs: impl IntoIterator<Item = (Option<isize>, Event)>) {
-        for (input, event) in pairs {
-            assert_eq!(cpu.execute(input).unwrap(), event);
+        for (mut input, event) in pairs {
+            assert_eq!(cpu.execute(&mut || input.take()).unwrap(), event);
         }
     }
 
@@ -551,7 +1942,45 @@ mod tests {
     }
 
     #[test]
-    fn rel_base_16_digits() {
+    fn run_collect_quine() {
+        let program = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut cpu = IntcodeComputer::new(program.clone());
+
+        assert_eq!(cpu.run_collect(None).unwrap(), program);
+    }
+
+    #[test]
+    fn run_collect_runs_out_of_input() {
+        let mut cpu = IntcodeComputer::new(vec![3, 0, 3, 0, 99]);
+
+        assert!(cpu.run_collect(vec![1]).is_err());
+    }
+
+    #[test]
+    fn outputs_iterator() {
+        let program = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut cpu = IntcodeComputer::new(program.clone());
+
+        let outputs = cpu.outputs(|| None).collect::<anyhow::Result<Vec<_>>>();
+        assert_eq!(outputs.unwrap(), program);
+    }
+
+    #[test]
+    fn outputs_iterator_wants_input() {
+        let mut cpu = IntcodeComputer::new(vec![104, 1, 3, 0, 99]);
+        let mut outputs = cpu.outputs(|| None);
+
+        assert_eq!(outputs.next().unwrap().unwrap(), 1);
+        assert!(outputs.next().unwrap().is_err());
+        assert!(outputs.next().is_none());
+    }
+
+    #[test]
+    fn rel_base_16_digits() {
         let program = vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0];
         let cpu = IntcodeComputer::new(program);
 
@@ -565,4 +1994,1261 @@ mod tests {
 
         io_halt(cpu, vec![(None, HaveOutput(1125899906842624)), END]);
     }
+
+    #[test]
+    fn display_operation() {
+        use super::Operation;
+        use std::convert::TryFrom;
+
+        assert_eq!(
+            Operation::try_from(1002).unwrap().to_string(),
+            "MUL pos imm pos"
+        );
+        assert_eq!(Operation::try_from(204).unwrap().to_string(), "OUT rel");
+        assert_eq!(Operation::try_from(99).unwrap().to_string(), "HLT");
+    }
+
+    #[test]
+    fn disassemble() {
+        let cpu = IntcodeComputer::new(vec![3, 9, 1008, 9, 8, 9, 204, -1, 99, 0]);
+
+        let lines: Vec<String> = cpu.disassemble().into_iter().map(|(_, l)| l).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "0000: STR [pos 9]",
+                "0002: EQ [pos 9] [imm 8] [pos 9]",
+                "0006: OUT [rel -1]",
+                "0008: HLT",
+                "0009: DATA 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn disassemble_data() {
+        let cpu = IntcodeComputer::new(vec![104, 5, 42, 99]);
+
+        assert_eq!(
+            cpu.disassemble(),
+            vec![
+                (0, "0000: OUT [imm 5]".to_owned()),
+                (2, "0002: DATA 42".to_owned()),
+                (3, "0003: DATA 99".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn day05_custom_io_closures() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let outputs = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&outputs);
+
+        let mut cpu = IntcodeComputer::new_with_io(
+            vec![3, 5, 4, 5, 99, 0],
+            || 6,
+            move |num| sink.borrow_mut().push(num),
+        );
+
+        cpu.execute_io().unwrap();
+
+        assert_eq!(*outputs.borrow(), vec![6]);
+        assert_eq!(cpu.memory.to_vec(), vec![3, 5, 4, 5, 99, 6]);
+    }
+
+    #[test]
+    fn day05_custom_io_replayed() {
+        use super::{recording_output, replay_input};
+
+        let mut input = replay_input(vec![6]);
+        let (output, outputs) = recording_output();
+        let mut cpu = IntcodeComputer::new_with_io(
+            vec![3, 5, 4, 5, 99, 0],
+            move || input().expect("ran out of replayed input"),
+            output,
+        );
+
+        cpu.execute_io().unwrap();
+
+        assert_eq!(*outputs.borrow(), vec![6]);
+    }
+
+    #[test]
+    fn day05_custom_io_writer() {
+        use std::cell::RefCell;
+        use std::io::{self, Write};
+        use std::rc::Rc;
+
+        /// A buffer the test can still read after the computer takes the writer.
+        struct Shared(Rc<RefCell<Vec<u8>>>);
+
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.borrow_mut().write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let written = Rc::new(RefCell::new(Vec::new()));
+        let mut cpu = IntcodeComputer::new_with_writer(
+            vec![3, 7, 4, 7, 104, -12, 99, 0],
+            || 6,
+            Shared(Rc::clone(&written)),
+        );
+
+        cpu.execute_io().unwrap();
+
+        assert_eq!(
+            String::from_utf8(written.borrow().clone()).unwrap(),
+            "6\n-12\n"
+        );
+    }
+
+    #[test]
+    fn no_input() {
+        use super::no_input;
+
+        let mut cpu = IntcodeComputer::new(vec![104, 1, 3, 0, 99]);
+        assert_eq!(cpu.execute(&mut no_input).unwrap(), HaveOutput(1));
+        assert_eq!(cpu.execute(&mut no_input).unwrap(), RequestingInput);
+    }
+
+    #[test]
+    fn once() {
+        use super::once;
+
+        let mut input = once(8);
+        let mut cpu = IntcodeComputer::new(vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8]);
+        assert_eq!(cpu.execute(&mut input).unwrap(), HaveOutput(1));
+        assert_eq!(cpu.execute(&mut input).unwrap(), Halted);
+
+        // a second read gets nothing
+        let mut input = once(8);
+        let mut cpu = IntcodeComputer::new(vec![3, 0, 3, 0, 99]);
+        assert_eq!(cpu.execute(&mut input).unwrap(), RequestingInput);
+    }
+
+    #[test]
+    fn replay_runs_out() {
+        use super::replay_input;
+
+        let mut input = replay_input(vec![1, 2]);
+        assert_eq!((input(), input(), input()), (Some(1), Some(2), None));
+    }
+
+    #[test]
+    fn execute_io_without_closures() {
+        let mut cpu = IntcodeComputer::new(vec![99]);
+
+        assert!(cpu.execute_io().is_err());
+    }
+
+    #[test]
+    fn search_inputs_finds_pair() {
+        use super::search_inputs;
+
+        // memory[0] = memory[noun] + memory[verb]
+        let program = vec![1, 0, 0, 0, 99, 10, 20, 30];
+
+        assert_eq!(search_inputs(&program, 1, 2, 0, 50, 0..=7), Some((6, 7)));
+        assert_eq!(search_inputs(&program, 1, 2, 0, 1000, 0..=7), None);
+    }
+
+    #[test]
+    fn parse_program() {
+        use super::parse_program;
+
+        assert_eq!(parse_program("1,2,3").unwrap(), vec![1, 2, 3]);
+        assert_eq!(parse_program("1, 2, 3\n").unwrap(), vec![1, 2, 3]);
+
+        let err = parse_program("1,x,3").unwrap_err().to_string();
+        assert!(err.contains("\"x\""), "{}", err);
+    }
+
+    #[test]
+    fn lines_input() {
+        use super::lines_input;
+        use std::io::Cursor;
+
+        let mut input = lines_input(Cursor::new("1\n -2 \n\nx\n3"));
+        assert_eq!(input(), Some(1));
+        assert_eq!(input(), Some(-2));
+        assert_eq!(input(), None);
+        assert_eq!(input(), None);
+        assert_eq!(input(), Some(3));
+        assert_eq!(input(), None);
+    }
+
+    #[test]
+    fn program_from_file() {
+        let path = std::env::temp_dir().join("intcode_program_from_file");
+        std::fs::write(&path, "1,0,0,0,99,\n\n").unwrap();
+
+        let program = super::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(program.unwrap(), vec![1, 0, 0, 0, 99]);
+    }
+
+    #[test]
+    fn execute_traced() {
+        let mut cpu = IntcodeComputer::new(vec![3, 9, 1008, 9, 8, 9, 4, 9, 99, 0]);
+        let mut trace = Vec::new();
+
+        let mut input = Some(8);
+        assert_eq!(
+            cpu.execute_traced(&mut || input.take(), &mut trace)
+                .unwrap(),
+            HaveOutput(1)
+        );
+        assert_eq!(
+            cpu.execute_traced(&mut || None, &mut trace).unwrap(),
+            Halted
+        );
+
+        assert_eq!(
+            String::from_utf8(trace).unwrap(),
+            "0000: STR pos [@9] rel_base=0\n\
+             0002: EQ pos imm pos [8, 8, @9] rel_base=0\n\
+             0006: OUT pos [1] rel_base=0\n\
+             0008: HLT [] rel_base=0\n"
+        );
+    }
+
+    #[test]
+    fn traced_waiting_store() {
+        // tracing a store that's still waiting for input doesn't touch its address
+        let mut cpu = IntcodeComputer::new(vec![3, 50, 99]);
+        let mut trace = Vec::new();
+
+        assert_eq!(
+            cpu.execute_traced(&mut || None, &mut trace).unwrap(),
+            RequestingInput
+        );
+        assert_eq!(
+            String::from_utf8(trace).unwrap(),
+            "0000: STR pos [@50] rel_base=0\n"
+        );
+        assert_eq!(cpu.max_addr_used(), 0);
+    }
+
+    #[test]
+    fn instruction_count() {
+        let mut cpu = IntcodeComputer::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+        assert_eq!(cpu.instruction_count(), 0);
+
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Halted);
+        // ADD, MUL, HLT
+        assert_eq!(cpu.instruction_count(), 3);
+    }
+
+    #[test]
+    fn opcode_counts() {
+        use super::Opcode;
+
+        let program = vec![
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99,
+        ];
+        let mut cpu = IntcodeComputer::new(program);
+
+        assert_eq!(cpu.run_collect(Some(9)).unwrap(), vec![1001]);
+
+        let counts = cpu.opcode_counts();
+        assert_eq!(counts.values().sum::<u64>(), cpu.instruction_count());
+        assert_eq!(counts[&Opcode::STR], 1);
+        assert_eq!(counts[&Opcode::HLT], 1);
+        assert!(!counts.contains_key(&Opcode::BAS));
+    }
+
+    #[test]
+    fn instruction_count_skips_waiting() {
+        let mut cpu = IntcodeComputer::new(vec![3, 0, 99]);
+
+        assert_eq!(cpu.execute(&mut || None).unwrap(), RequestingInput);
+        assert_eq!(cpu.instruction_count(), 0);
+
+        assert_eq!(cpu.execute(&mut || Some(1)).unwrap(), Halted);
+        assert_eq!(cpu.instruction_count(), 2);
+    }
+
+    #[test]
+    fn checked_overflow() {
+        let program = vec![1102, isize::MAX, 2, 0, 99];
+
+        let mut cpu = IntcodeComputer::new_checked(program.clone());
+        let err = cpu.execute(&mut || None).unwrap_err().to_string();
+        assert!(err.contains("MUL overflowed at pc 0"), "{}", err);
+
+        let mut cpu = IntcodeComputer::new(program);
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Halted);
+        assert_eq!(cpu.peek(0), -2);
+    }
+
+    #[test]
+    fn pc_out_of_bounds() {
+        let mut cpu = IntcodeComputer::new(vec![1, 0, 0, 0]);
+
+        let err = cpu.execute(&mut || None).unwrap_err().to_string();
+        assert_eq!(err, "pc 4 out of bounds (memory len 4)");
+    }
+
+    #[test]
+    fn argument_out_of_bounds() {
+        let mut cpu = IntcodeComputer::new(vec![1, 0, 0]);
+
+        let err = cpu.execute(&mut || None).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "argument address 3 out of bounds (memory len 3) at pc 0"
+        );
+    }
+
+    #[test]
+    fn breakpoint() {
+        let mut cpu = IntcodeComputer::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+        cpu.add_breakpoint(4);
+
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Breakpoint(4));
+        assert_eq!(cpu.pc, 4);
+        assert_eq!(cpu.peek(3), 70);
+        assert_eq!(cpu.peek(0), 1);
+
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Halted);
+        assert_eq!(cpu.peek(0), 3500);
+    }
+
+    #[test]
+    fn breakpoint_in_loop() {
+        // count memory[13] down from 2 to 0, looping back to address 0
+        let program = vec![1001, 13, -1, 13, 1005, 13, 0, 104, 7, 99, 0, 0, 0, 2];
+        let mut cpu = IntcodeComputer::new(program);
+        cpu.add_breakpoint(0);
+
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Breakpoint(0));
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Breakpoint(0));
+        assert_eq!(cpu.peek(13), 1);
+
+        cpu.remove_breakpoint(0);
+        assert_eq!(cpu.execute(&mut || None).unwrap(), HaveOutput(7));
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Halted);
+    }
+
+    #[test]
+    fn single_step() {
+        let mut cpu = IntcodeComputer::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+
+        assert_eq!(cpu.step(&mut || None).unwrap(), None);
+        assert_eq!(cpu.pc, 4);
+        assert_eq!(cpu.peek(3), 70);
+
+        assert_eq!(cpu.step(&mut || None).unwrap(), None);
+        assert_eq!(cpu.peek(0), 3500);
+
+        assert_eq!(cpu.step(&mut || None).unwrap(), Some(Halted));
+    }
+
+    #[test]
+    fn reset() {
+        let mut cpu = IntcodeComputer::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Halted);
+        let first = cpu.memory.to_vec();
+
+        cpu.reset();
+        assert_eq!(cpu.pc, 0);
+        assert_eq!(cpu.peek(0), 1);
+
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Halted);
+        assert_eq!(cpu.memory.to_vec(), first);
+    }
+
+    const QUINE: [isize; 16] = [
+        109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+    ];
+
+    #[test]
+    fn state_round_trip() {
+        let mut cpu: IntcodeComputer = QUINE[..].into();
+        for _ in 0..5 {
+            cpu.execute(&mut || None).unwrap();
+        }
+
+        let mut resumed = IntcodeComputer::from_state(cpu.to_state());
+        assert_eq!(resumed.run_collect(None).unwrap(), QUINE[5..].to_vec());
+
+        cpu.run_collect(None).unwrap();
+        assert_eq!(resumed.memory, cpu.memory);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn state_through_json() {
+        use super::State;
+
+        let mut uninterrupted = IntcodeComputer::from(&QUINE[..]);
+        let expected = uninterrupted.run_collect(None).unwrap();
+
+        let mut cpu = IntcodeComputer::from(&QUINE[..]);
+        let mut outputs = Vec::new();
+        for _ in 0..5 {
+            if let HaveOutput(x) = cpu.execute(&mut || None).unwrap() {
+                outputs.push(x);
+            }
+        }
+
+        let json = serde_json::to_string(&cpu.to_state()).unwrap();
+        let state: State = serde_json::from_str(&json).unwrap();
+        let mut resumed = IntcodeComputer::from_state(state);
+        outputs.extend(resumed.run_collect(None).unwrap());
+
+        assert_eq!(outputs, expected);
+        assert_eq!(resumed.memory, uninterrupted.memory);
+    }
+
+    #[test]
+    fn ascii_echo() {
+        // echo characters until a newline, then output 1000
+        let program = vec![
+            3, 100, 4, 100, 1008, 100, 10, 101, 1006, 101, 0, 104, 1000, 99,
+        ];
+        let mut cpu = IntcodeComputer::new(program);
+
+        assert_eq!(cpu.run_ascii().unwrap(), (String::new(), vec![]));
+
+        cpu.feed_line("hi");
+        assert_eq!(cpu.run_ascii().unwrap(), ("hi\n".to_owned(), vec![1000]));
+    }
+
+    #[test]
+    fn negative_jump_target() {
+        let mut cpu = IntcodeComputer::new(vec![1101, 0, 0, 10, 1105, 1, -3, 99]);
+
+        let err = cpu.execute(&mut || None).unwrap_err().to_string();
+        assert_eq!(err, "illegal negative address -3 from arg 2 at pc 4");
+    }
+
+    #[test]
+    fn negative_store_address() {
+        let mut cpu = IntcodeComputer::new(vec![109, -5, 21101, 1, 2, 0, 99]);
+
+        let err = cpu.execute(&mut || None).unwrap_err().to_string();
+        assert_eq!(err, "illegal negative address -5 from arg 3 at pc 2");
+    }
+
+    #[test]
+    fn memory_limit() {
+        let mut cpu = IntcodeComputer::new(vec![1101, 1, 2, 5000, 99]).with_memory_limit(1024);
+
+        let err = cpu.execute(&mut || None).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "write to address 5000 exceeds memory limit 1024 at pc 0"
+        );
+        assert_eq!(cpu.memory.len(), 5);
+    }
+
+    #[test]
+    fn memory_limit_allows_reads() {
+        let mut cpu = IntcodeComputer::new(vec![1, 5000, 5001, 0, 99]).with_memory_limit(1024);
+
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Halted);
+        assert_eq!(cpu.peek(0), 0);
+    }
+
+    #[test]
+    fn wide_cells() {
+        // 2^62 squared only fits in an i128
+        let mut cpu = IntcodeComputer::<i128>::new(vec![2, 5, 5, 5, 99, 1 << 62]);
+
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Halted);
+        assert_eq!(cpu.peek(5), 1 << 124);
+    }
+
+    #[test]
+    fn narrow_cells() {
+        // outputs whether the input equals 8
+        let program = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+
+        let mut cpu = IntcodeComputer::<i16>::new(program.clone());
+        assert_eq!(cpu.run_collect(Some(8)).unwrap(), vec![1]);
+
+        let mut cpu = IntcodeComputer::<i16>::new(program);
+        assert_eq!(cpu.run_collect(Some(7)).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn execute_bounded() {
+        let mut cpu = IntcodeComputer::new(vec![1105, 1, 0]);
+        assert!(cpu.execute_bounded(&mut || None, 1000).is_err());
+        assert_eq!(cpu.instruction_count(), 1000);
+
+        let mut cpu = IntcodeComputer::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+        assert_eq!(cpu.execute_bounded(&mut || None, 1000).unwrap(), Halted);
+    }
+
+    #[test]
+    fn set_pc() {
+        // output 1, then output 2
+        let mut cpu = IntcodeComputer::new(vec![104, 1, 104, 2, 99]);
+
+        assert!(cpu.set_pc(5).is_err());
+        assert_eq!(cpu.pc(), 0);
+
+        cpu.set_pc(2).unwrap();
+        assert_eq!(cpu.run_collect(None).unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn traced_matches_untraced() {
+        // tracing decodes every mode up front, plain execution only the ones it uses
+        let mut plain = IntcodeComputer::from(&QUINE[..]);
+        let plain_outputs = plain.run_collect(None).unwrap();
+
+        let mut traced = IntcodeComputer::from(&QUINE[..]);
+        let mut traced_outputs = Vec::new();
+        loop {
+            match traced
+                .execute_traced(&mut || None, &mut std::io::sink())
+                .unwrap()
+            {
+                HaveOutput(x) => traced_outputs.push(x),
+                Halted => break,
+                event => panic!("unexpected {:?}", event),
+            }
+        }
+
+        assert_eq!(traced_outputs, plain_outputs);
+        assert_eq!(traced.memory, plain.memory);
+    }
+
+    #[test]
+    fn diff_memory() {
+        use super::diff_memory;
+
+        let program = vec![1, 1, 1, 4, 99, 5, 6, 0, 99];
+        let mut cpu = IntcodeComputer::new(program.clone());
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Halted);
+
+        // day02_smol_4: the ADD turns the HLT at 4 into a MUL, which writes 30 to 0
+        assert_eq!(
+            diff_memory(&program, &cpu.memory.to_vec()),
+            vec![(0, 1, 30), (4, 99, 2)]
+        );
+        assert_eq!(diff_memory(&[1, 2], &[1, 2, 0, 3]), vec![(3, 0, 3)]);
+    }
+
+    #[test]
+    fn self_modification() {
+        // output 7, then overwrite the OUT at 0
+        let program = vec![104, 7, 1101, 1, 1, 0, 99];
+
+        let mut cpu = IntcodeComputer::builder(program.clone())
+            .detect_self_modification()
+            .build();
+        assert_eq!(cpu.execute(&mut || None).unwrap(), HaveOutput(7));
+        assert_eq!(
+            cpu.execute(&mut || None).unwrap(),
+            SelfModified { addr: 0, pc: 2 }
+        );
+        assert_eq!(cpu.peek(0), 2);
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Halted);
+
+        let mut cpu = IntcodeComputer::new(program);
+        assert_eq!(cpu.run_collect(None).unwrap(), vec![7]);
+    }
+
+    #[test]
+    fn dump() {
+        let mut cpu = IntcodeComputer::new(vec![1, 9, 10, 3, 1002, 3, 11, 0, 99, 30, 40, 50]);
+        cpu.step(&mut || None).unwrap();
+
+        assert_eq!(
+            cpu.dump(2),
+            "pc 4, rel_base 0\n  \
+             0002: 10\n  \
+             0003: 70\n\
+             > 0004: 1002 (MUL pos imm pos)\n  \
+             0005: 3\n  \
+             0006: 11\n"
+        );
+        assert!(cpu.dump(10).starts_with("pc 4, rel_base 0\n  0000: 1\n"));
+    }
+
+    #[test]
+    fn validate() {
+        assert!(
+            IntcodeComputer::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50])
+                .validate()
+                .is_ok()
+        );
+
+        let err = IntcodeComputer::new(vec![103, 5, 99])
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("STR at 0"), "{}", err);
+
+        assert!(IntcodeComputer::new(vec![1, 0, 0, 0, 42])
+            .validate()
+            .is_err());
+        assert!(IntcodeComputer::new(vec![11101, 1, 1, 0, 99])
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn state_report() {
+        let mut cpu = IntcodeComputer::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+        cpu.add_breakpoint(8);
+        cpu.step(&mut || None).unwrap();
+
+        assert_eq!(
+            cpu.state_report(5),
+            "pc 4, rel_base 0, 1 instructions executed\n  \
+             0004: MUL [pos 3] [pos 11] [pos 0]\n  \
+             0008: HLT\n\
+             breakpoints: [8]\n"
+        );
+    }
+
+    #[test]
+    fn halt_leaves_pc() {
+        let mut cpu = IntcodeComputer::new(vec![1101, 1, 2, 0, 99]);
+
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Halted);
+        assert_eq!(cpu.pc(), 4);
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Halted);
+        assert_eq!(cpu.pc(), 4);
+    }
+
+    #[test]
+    fn is_halted() {
+        let mut cpu = IntcodeComputer::new(vec![4, 0, 99]);
+
+        assert!(!cpu.is_halted());
+        assert_eq!(cpu.execute(&mut || None).unwrap(), HaveOutput(4));
+        assert!(cpu.is_halted());
+        assert_eq!(cpu.execute(&mut || None).unwrap(), Halted);
+        assert!(cpu.is_halted());
+
+        // a pc on garbage isn't halted, just broken
+        assert!(!IntcodeComputer::new(vec![-1]).is_halted());
+    }
+
+    #[test]
+    fn execute_until_halt() {
+        let program = vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0];
+        let mut cpu: IntcodeComputer = IntcodeComputer::new(program);
+
+        let summary = cpu.execute_until_halt(None).unwrap();
+        assert_eq!(summary.outputs, vec![1219070632396864]);
+        assert_eq!(summary.instructions, 3);
+    }
+
+    #[test]
+    fn input_policy() {
+        use super::{InputPolicy, IntcodeError};
+
+        // output each input until given a 0
+        let program = vec![3, 20, 1006, 20, 10, 4, 20, 1105, 1, 0, 99];
+
+        let mut cpu = IntcodeComputer::new(program.clone());
+        let mut inputs = vec![3, 4].into_iter();
+        let mut input = || inputs.next();
+        let policy = InputPolicy::ReturnZero;
+        assert_eq!(
+            cpu.execute_with_policy(&mut input, policy).unwrap(),
+            HaveOutput(3)
+        );
+        assert_eq!(
+            cpu.execute_with_policy(&mut input, policy).unwrap(),
+            HaveOutput(4)
+        );
+        assert_eq!(cpu.execute_with_policy(&mut input, policy).unwrap(), Halted);
+        assert_eq!(cpu.pc(), 10);
+
+        let mut cpu = IntcodeComputer::new(program.clone());
+        assert_eq!(
+            cpu.execute_with_policy(&mut || None, InputPolicy::Halt)
+                .unwrap(),
+            Halted
+        );
+        assert_eq!(cpu.pc(), 0);
+
+        let mut cpu = IntcodeComputer::new(program);
+        assert_eq!(
+            cpu.execute_with_policy(&mut || None, InputPolicy::default()),
+            Err(IntcodeError::InputExhausted { pc: 0 })
+        );
+    }
+
+    #[test]
+    fn unused_modes() {
+        use super::Operation;
+        use std::convert::TryFrom;
+
+        assert!(Operation::try_from(11102).is_ok());
+        assert!(Operation::try_from(1105).is_ok());
+        assert!(Operation::try_from(104).is_ok());
+
+        for &word in &[111102isize, 11105, 1104, 199] {
+            let err = Operation::try_from(word).err().unwrap();
+            assert!(err
+                .to_string()
+                .contains("Nonzero mode for unused parameter"));
+        }
+    }
+
+    #[test]
+    fn operation_modes() {
+        use super::{Mode, Opcode, Operation};
+        use std::convert::TryFrom;
+
+        let operation = Operation::try_from(1002).unwrap();
+        assert_eq!(operation.opcode, Opcode::MUL);
+        assert_eq!(
+            operation.modes,
+            [Mode::Position, Mode::Immediate, Mode::Position]
+        );
+        assert_eq!(operation.mode(2), Mode::Immediate);
+    }
+
+    #[test]
+    fn pipe() {
+        // output double each input, stopping after a 0
+        let mut double = IntcodeComputer::new(vec![
+            3, 15, 1002, 15, 2, 15, 4, 15, 1006, 15, 14, 1105, 1, 0, 99, 0,
+        ]);
+        // output one more than each input, stopping at a 0
+        let mut increment = IntcodeComputer::new(vec![
+            3, 15, 1006, 15, 14, 1001, 15, 1, 15, 4, 15, 1105, 1, 0, 99, 0,
+        ]);
+
+        double.push_inputs(vec![1, 2, 3, 0]);
+        assert_eq!(
+            super::pipe(&mut double, &mut increment).unwrap(),
+            vec![3, 5, 7]
+        );
+
+        // the doubler runs out of input without seeing a 0
+        double.reset();
+        double.push_input(1);
+        assert!(super::pipe(&mut double, &mut increment).is_err());
+    }
+
+    #[test]
+    fn max_addr_used() {
+        let mut cpu = IntcodeComputer::from(&QUINE[..]);
+        assert_eq!(cpu.max_addr_used(), 0);
+
+        cpu.run_collect(None).unwrap();
+        // the quine keeps its counter at 100 and its comparison at 101
+        assert_eq!(cpu.max_addr_used(), 101);
+
+        cpu.reset();
+        assert_eq!(cpu.max_addr_used(), 0);
+    }
+
+    #[test]
+    fn effective_addr() {
+        use super::Operation;
+        use std::convert::TryFrom;
+
+        // BAS 100; ADD [rel 1] [imm 7] [pos 3]
+        let mut cpu = IntcodeComputer::new(vec![109, 100, 1201, 1, 7, 3, 99]);
+        cpu.step(&mut || None).unwrap();
+
+        let operation = Operation::try_from(cpu.peek(cpu.pc())).unwrap();
+        assert_eq!(cpu.effective_addr(&operation, 1).unwrap(), Some(101));
+        assert_eq!(cpu.effective_addr(&operation, 2).unwrap(), None);
+        assert_eq!(cpu.effective_addr(&operation, 3).unwrap(), Some(3));
+    }
+
+    #[test]
+    fn events_are_copy() {
+        let mut cpu = IntcodeComputer::new(vec![104, 7, 99]);
+
+        let event = cpu.execute(&mut || None).unwrap();
+        let last_event = event;
+        let events = vec![event, last_event];
+        assert_eq!(events, vec![HaveOutput(7), HaveOutput(7)]);
+        assert_eq!(event, HaveOutput(7));
+    }
+
+    #[test]
+    fn next_n_outputs() {
+        // output (1, 2, 3) and (4, 5, 6)
+        let program = vec![104, 1, 104, 2, 104, 3, 104, 4, 104, 5, 104, 6, 99];
+        let mut cpu = IntcodeComputer::new(program);
+
+        assert_eq!(
+            cpu.next_n_outputs(3, &mut || None).unwrap(),
+            Some(vec![1, 2, 3])
+        );
+        assert_eq!(
+            cpu.next_n_outputs(3, &mut || None).unwrap(),
+            Some(vec![4, 5, 6])
+        );
+        assert_eq!(cpu.next_n_outputs(3, &mut || None).unwrap(), None);
+
+        let mut cpu = IntcodeComputer::new(vec![104, 1, 99]);
+        assert!(cpu.next_n_outputs(3, &mut || None).is_err());
+    }
+
+    #[test]
+    fn error_kinds() {
+        use super::{IntcodeError::*, Opcode};
+
+        let run = |program: Vec<isize>| {
+            IntcodeComputer::new(program)
+                .execute(&mut || None)
+                .unwrap_err()
+        };
+
+        assert_eq!(run(vec![98]), UnknownOpcode(98));
+        assert_eq!(run(vec![301, 0, 0, 0]), UnknownMode(3));
+        assert_eq!(run(vec![-1]), NegativeInstruction(-1));
+        assert_eq!(
+            run(vec![1105, 1, -3]),
+            IllegalAddress {
+                addr: -3,
+                param: 2,
+                pc: 0
+            }
+        );
+        assert_eq!(run(vec![11101, 1, 1, 0]), ImmediateStore { pc: 0 });
+        assert_eq!(run(vec![1, 0, 0, 0]), PcOutOfBounds { pc: 4, len: 4 });
+
+        let mut cpu = IntcodeComputer::new_checked(vec![1102, isize::MAX, 2, 0, 99]);
+        assert_eq!(
+            cpu.execute(&mut || None).unwrap_err(),
+            Overflow {
+                opcode: Opcode::MUL,
+                pc: 0,
+                left: isize::MAX as i128,
+                right: 2
+            }
+        );
+    }
+
+    #[test]
+    fn error_kinds_through_anyhow() {
+        use super::IntcodeError;
+
+        let err = IntcodeComputer::new(vec![98])
+            .run_collect(None)
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<IntcodeError>(),
+            Some(&IntcodeError::UnknownOpcode(98))
+        );
+    }
+
+    #[test]
+    fn program_string_round_trip() {
+        use super::parse_program;
+
+        let mut cpu = IntcodeComputer::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+        cpu.execute(&mut || None).unwrap();
+
+        let dumped = cpu.to_program_string();
+        assert_eq!(dumped, "3500,9,10,70,2,3,11,0,99,30,40,50");
+        assert_eq!(parse_program(&dumped).unwrap(), cpu.memory.to_vec());
+    }
+
+    #[test]
+    fn tee_echo() {
+        use super::{recording_output, replay_input, tee_io};
+
+        let mut log = Vec::new();
+        let (recorder, outputs) = recording_output();
+        let (mut input, mut output) = tee_io(replay_input(vec![5, 999]), recorder, &mut log);
+
+        let mut cpu = IntcodeComputer::new(vec![3, 7, 4, 7, 1105, 1, 0, 0]);
+        for _ in 0..2 {
+            match cpu.execute(&mut input).unwrap() {
+                HaveOutput(x) => output(x),
+                event => panic!("unexpected {:?}", event),
+            }
+        }
+        assert_eq!(cpu.execute(&mut input).unwrap(), RequestingInput);
+        drop((input, output));
+
+        assert_eq!(*outputs.borrow(), vec![5, 999]);
+        assert_eq!(String::from_utf8(log).unwrap(), "> 5\n< 5\n> 999\n< 999\n");
+    }
+
+    #[test]
+    fn quines() {
+        use super::is_quine;
+
+        assert!(is_quine(&QUINE).unwrap());
+        assert!(!is_quine(&[104, 1, 99]).unwrap());
+        assert!(is_quine(&[3, 0, 99]).is_err());
+    }
+
+    #[test]
+    fn shared_copy_on_write() {
+        use super::Memory;
+        use std::sync::Arc;
+
+        let program: Arc<[isize]> = Arc::from(&[1, 0, 0, 0, 99][..]);
+        let mut writer = IntcodeComputer::from_shared(Arc::clone(&program));
+        let reader = IntcodeComputer::from_shared(Arc::clone(&program));
+
+        assert_eq!(writer.execute(&mut || None).unwrap(), Halted);
+        assert_eq!(writer.memory.to_vec(), vec![2, 0, 0, 0, 99]);
+        assert!(matches!(writer.memory, Memory::Dense(_)));
+
+        assert_eq!(reader.peek(0), 1);
+        assert!(matches!(reader.memory, Memory::Shared(_)));
+        assert_eq!(program[0], 1);
+
+        writer.reset();
+        assert_eq!(writer.peek(0), 1);
+    }
+
+    #[test]
+    fn run_for_result() {
+        let mut cpu = IntcodeComputer::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+        assert_eq!(cpu.run_for_result().unwrap(), 3500);
+
+        let mut cpu = IntcodeComputer::new(vec![3, 0, 99]);
+        assert!(cpu.run_for_result().is_err());
+    }
+
+    #[test]
+    fn batched_quine() {
+        let mut cpu = IntcodeComputer::from(&QUINE[..]);
+        let mut batches = Vec::new();
+        cpu.execute_batched(&mut || None, 5, |batch| batches.push(batch.to_vec()))
+            .unwrap();
+
+        assert_eq!(
+            batches.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![5, 5, 5, 1]
+        );
+        assert_eq!(batches.concat(), QUINE.to_vec());
+    }
+
+    #[test]
+    fn load_past_end() {
+        let mut cpu = IntcodeComputer::new(vec![1, 2, 3]);
+        cpu.load_at(5, &[7, 8, 9]);
+
+        assert_eq!(cpu.memory.to_vec(), vec![1, 2, 3, 0, 0, 7, 8, 9]);
+        assert_eq!(cpu.peek(6), 8);
+    }
+
+    #[test]
+    fn resume() {
+        // add one to each input
+        let mut cpu: IntcodeComputer =
+            IntcodeComputer::new(vec![3, 11, 101, 1, 11, 11, 4, 11, 1105, 1, 0, 0]);
+
+        assert_eq!(cpu.resume(Some(5)).unwrap(), HaveOutput(6));
+        assert_eq!(cpu.resume(None).unwrap(), RequestingInput);
+        assert_eq!(cpu.resume(Some(9)).unwrap(), HaveOutput(10));
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        use super::{from_bytes, to_bytes};
+
+        let program = vec![104, 1125899906842624, -99, isize::MIN, 99];
+        let bytes = to_bytes(&program);
+        assert_eq!(&bytes[..5], b"INTC\x01");
+        assert_eq!(bytes.len(), 5 + 5 * 8);
+        assert_eq!(from_bytes(&bytes).unwrap(), program);
+
+        assert!(from_bytes(b"INTC").is_err());
+        assert!(from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] = 2;
+        assert!(from_bytes(&wrong_version).is_err());
+        let mut wrong_magic = bytes;
+        wrong_magic[0] = b'X';
+        assert!(from_bytes(&wrong_magic).is_err());
+    }
+
+    #[test]
+    fn execute_from_iter() {
+        // 1 if the input is 8, 0 otherwise
+        let mut cpu = IntcodeComputer::new(vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8]);
+        let mut inputs = vec![8isize].into_iter();
+
+        assert_eq!(cpu.execute_from_iter(&mut inputs).unwrap(), HaveOutput(1));
+        assert_eq!(cpu.execute_from_iter(&mut inputs).unwrap(), Halted);
+
+        cpu.reset();
+        assert_eq!(cpu.execute_from_iter(&mut inputs).unwrap(), RequestingInput);
+    }
+
+    #[test]
+    fn cycles() {
+        // two MULs, then halt
+        let mut mul_heavy = IntcodeComputer::new(vec![1002, 9, 2, 9, 1002, 9, 2, 9, 99, 1]);
+        // two jumps, then halt
+        let mut jump_heavy = IntcodeComputer::new(vec![1105, 1, 3, 1105, 1, 6, 99]);
+        mul_heavy.run_collect(None).unwrap();
+        jump_heavy.run_collect(None).unwrap();
+
+        assert_eq!(
+            mul_heavy.instruction_count(),
+            jump_heavy.instruction_count()
+        );
+        assert_eq!(mul_heavy.cycles(), 11);
+        assert_eq!(jump_heavy.cycles(), 3);
+
+        mul_heavy.reset();
+        assert_eq!(mul_heavy.cycles(), 0);
+    }
+
+    #[test]
+    fn watch() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut cpu = IntcodeComputer::new(vec![1, 0, 0, 0, 99]);
+        for _ in 0..2 {
+            let sink = Rc::clone(&seen);
+            cpu.watch(0, move |old, new| sink.borrow_mut().push((old, new)));
+        }
+        let unwatched = Rc::clone(&seen);
+        cpu.watch(1, move |old, new| unwatched.borrow_mut().push((old, new)));
+
+        cpu.run_collect(None).unwrap();
+        assert_eq!(*seen.borrow(), vec![(1, 2), (1, 2)]);
+    }
+
+    #[test]
+    fn rel_base_overflow() {
+        use super::{IntcodeError, Opcode};
+
+        let mut cpu = IntcodeComputer::new(vec![109, isize::MAX, 109, 1, 99]);
+        assert_eq!(
+            cpu.execute(&mut || None),
+            Err(IntcodeError::Overflow {
+                opcode: Opcode::BAS,
+                pc: 2,
+                left: isize::MAX as i128,
+                right: 1,
+            })
+        );
+
+        let mut cpu = IntcodeComputer::new(vec![109, isize::MAX, 204, 1, 99]);
+        assert!(matches!(
+            cpu.execute(&mut || None),
+            Err(IntcodeError::AddressTooLarge { pc: 2, .. })
+        ));
+    }
+
+    /// A tiny xorshift generator, so the fuzz test is repeatable without a dependency.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        /// Mostly plausible cells, with the occasional wild one.
+        fn cell(&mut self) -> isize {
+            match self.below(4) {
+                0 => self.below(30) as isize - 10,
+                1 => {
+                    let opcodes = [1, 2, 3, 4, 5, 6, 7, 8, 9, 99];
+                    let modes = self.below(3) * 100 + self.below(3) * 1000 + self.below(3) * 10000;
+                    (modes + opcodes[self.below(opcodes.len())]) as isize
+                }
+                2 => self.next() as isize,
+                _ => self.below(64) as isize,
+            }
+        }
+    }
+
+    #[test]
+    fn fuzz() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let corpus: Vec<Vec<isize>> = vec![
+            QUINE.to_vec(),
+            vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50],
+            vec![1, 0, 0, 0, 99],
+            vec![2, 3, 0, 3, 99],
+            vec![2, 4, 4, 5, 99, 0],
+            vec![1, 1, 1, 4, 99, 5, 6, 0, 99],
+            vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8],
+            vec![3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1],
+            vec![104, 1125899906842624, 99],
+            vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0],
+        ];
+
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..5000 {
+            let mut program = if rng.below(4) == 0 {
+                let len = rng.below(32) + 1;
+                (0..len).map(|_| rng.cell()).collect()
+            } else {
+                corpus[rng.below(corpus.len())].clone()
+            };
+            for _ in 0..rng.below(4) {
+                let idx = rng.below(program.len());
+                program[idx] = rng.cell();
+            }
+            let inputs: Vec<isize> = (0..rng.below(4)).map(|_| rng.cell()).collect();
+
+            let run = catch_unwind(AssertUnwindSafe(|| {
+                let mut cpu = IntcodeComputer::new(program.clone()).with_memory_limit(4096);
+                let mut inputs = inputs.iter().copied();
+                for _ in 0..100 {
+                    match cpu.execute_bounded(&mut || inputs.next(), 1000) {
+                        Ok(Event::Halted) | Ok(Event::RequestingInput) | Err(_) => break,
+                        Ok(_) => continue,
+                    }
+                }
+            }));
+            assert!(
+                run.is_ok(),
+                "panicked on {:?} with inputs {:?}",
+                program,
+                inputs
+            );
+        }
+    }
+
+    #[test]
+    fn rel_base_history() {
+        let mut cpu = IntcodeComputer::builder(QUINE.to_vec())
+            .record_rel_base()
+            .build();
+        assert!(cpu.rel_base_history().is_empty());
+
+        cpu.run_collect(None).unwrap();
+        let history = cpu.rel_base_history();
+        assert_eq!(history.len(), 16);
+        assert_eq!(history[0], (0, 0, 1));
+        assert_eq!(history[15], (0, 15, 16));
+
+        let mut unrecorded = IntcodeComputer::from(&QUINE[..]);
+        unrecorded.run_collect(None).unwrap();
+        assert!(unrecorded.rel_base_history().is_empty());
+    }
+
+    #[test]
+    fn operation_raw() {
+        use super::Operation;
+        use std::convert::TryFrom;
+
+        assert_eq!(Operation::try_from(1002).unwrap().raw, 1002);
+        assert_eq!(Operation::try_from(99i64).unwrap().raw, 99);
+    }
+
+    #[test]
+    fn compare_runs() {
+        use super::compare_runs;
+
+        let equals_8 = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        compare_runs(equals_8.clone(), equals_8.clone(), vec![8]).unwrap();
+        compare_runs(QUINE.to_vec(), QUINE.to_vec(), vec![]).unwrap();
+
+        let less_than_8 = vec![3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8];
+        let err = compare_runs(equals_8.clone(), less_than_8, vec![3]).unwrap_err();
+        assert_eq!(err.to_string(), "Output 0 differs: 0 vs 1");
+
+        let err = compare_runs(vec![104, 1, 99], vec![104, 1, 104, 2, 99], vec![]).unwrap_err();
+        assert_eq!(err.to_string(), "Output 1 differs: nothing vs 2");
+
+        let err = compare_runs(equals_8, vec![3, 0, 104, 1, 3, 0, 99], vec![8]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Both output the same, but the first halted and the second ran out of input"
+        );
+    }
+
+    #[test]
+    fn read_program_whitespace() {
+        use super::read_program;
+
+        assert_eq!(read_program(&b"3,\n5,\n4\n"[..]).unwrap(), vec![3, 5, 4]);
+        assert_eq!(
+            read_program(&b"  1, \n42,\t-7 ,,\n"[..]).unwrap(),
+            vec![1, 42, -7]
+        );
+        assert!(read_program(&b"1,2 3"[..]).is_err());
+    }
+
+    #[test]
+    fn peek_poke() {
+        let mut cpu = IntcodeComputer::new(vec![1, 2, 3]);
+
+        assert_eq!(cpu.peek(1), 2);
+        assert_eq!(cpu.peek(100), 0);
+
+        cpu.poke(0, 7);
+        cpu.poke(6, 9);
+
+        assert_eq!(cpu.peek(0), 7);
+        assert_eq!(cpu.memory.to_vec(), vec![7, 2, 3, 0, 0, 0, 9]);
+    }
+
+    #[test]
+    fn buffered_input() {
+        let program = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        let mut cpu = IntcodeComputer::new(program);
+
+        assert_eq!(cpu.execute_buffered().unwrap(), RequestingInput);
+
+        cpu.push_input(8);
+        assert_eq!(cpu.execute_buffered().unwrap(), HaveOutput(1));
+        assert_eq!(cpu.execute_buffered().unwrap(), Halted);
+    }
+
+    #[test]
+    fn buffered_inputs_in_order() {
+        // read two numbers and output their difference
+        let program = vec![
+            3, 15, 3, 16, 1002, 16, -1, 16, 1, 15, 16, 15, 4, 15, 99, 0, 0,
+        ];
+        let mut cpu = IntcodeComputer::new(program);
+
+        cpu.push_inputs(vec![10, 3]);
+        assert_eq!(cpu.execute_buffered().unwrap(), HaveOutput(7));
+        assert_eq!(cpu.execute_buffered().unwrap(), Halted);
+    }
+
+    #[test]
+    fn sparse_high_address() {
+        // store 7 at address 1_000_000, then read it back out
+        let program = vec![1101, 3, 4, 1_000_000, 4, 1_000_000, 99];
+        let cpu = IntcodeComputer::new_sparse(program);
+
+        io_halt(cpu, vec![(None, HaveOutput(7)), END]);
+    }
+
+    #[test]
+    fn sparse_matches_dense() {
+        let program = vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+
+        let mut dense = IntcodeComputer::new(program.clone());
+        let mut sparse = IntcodeComputer::new_sparse(program);
+
+        assert_eq!(dense.execute(&mut || None).unwrap(), Halted);
+        assert_eq!(sparse.execute(&mut || None).unwrap(), Halted);
+        assert_eq!(dense.memory.to_vec(), sparse.memory.to_vec());
+    }
 }
@@ -0,0 +1,97 @@
+use anyhow::{format_err, Result};
+use intcode::*;
+use std::str::FromStr;
+
+const USAGE: &str = "usage: intcode [--ascii] [--dump-memory] <program> [-- <input>...]";
+
+struct Args {
+    path: String,
+    inputs: Vec<isize>,
+    ascii: bool,
+    /// Print memory as a program after the run.
+    dump_memory: bool,
+}
+
+/// Flags and the program path come before `--`, inputs after.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args> {
+    let mut args = args.into_iter();
+    let mut path = None;
+    let mut ascii = false;
+    let mut dump_memory = false;
+
+    for arg in &mut args {
+        match arg.as_str() {
+            "--" => break,
+            "--ascii" => ascii = true,
+            "--dump-memory" => dump_memory = true,
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(format_err!("Unexpected argument {:?}\n{}", arg, USAGE)),
+        }
+    }
+
+    let inputs = args
+        .map(|arg| {
+            isize::from_str(&arg)
+                .map_err(|_| format_err!("Input must be an integer, got {:?}", arg))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(Args {
+        path: path.ok_or_else(|| format_err!("{}", USAGE))?,
+        inputs,
+        ascii,
+        dump_memory,
+    })
+}
+
+fn main() -> Result<()> {
+    let args = parse_args(std::env::args().skip(1))?;
+    let mut cpu = IntcodeComputer::new(from_file(&args.path)?);
+
+    if args.ascii {
+        cpu.push_inputs(args.inputs);
+        let (text, values) = cpu.run_ascii()?;
+        print!("{}", text);
+        for value in values {
+            println!("{}", value);
+        }
+    } else {
+        for output in cpu.run_collect(args.inputs)? {
+            println!("{}", output);
+        }
+    }
+
+    if args.dump_memory {
+        println!("{}", cpu.to_program_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_args;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|&arg| arg.to_owned()).collect()
+    }
+
+    #[test]
+    fn inputs_after_separator() {
+        let parsed = parse_args(args(&["--ascii", "prog.txt", "--", "1", "-2", "3"])).unwrap();
+
+        assert_eq!(parsed.path, "prog.txt");
+        assert_eq!(parsed.inputs, vec![1, -2, 3]);
+        assert!(parsed.ascii);
+        assert!(!parsed.dump_memory);
+
+        let parsed = parse_args(args(&["prog.txt", "--dump-memory"])).unwrap();
+        assert!(parsed.dump_memory);
+    }
+
+    #[test]
+    fn bad_args() {
+        assert!(parse_args(args(&[])).is_err());
+        assert!(parse_args(args(&["a", "b"])).is_err());
+        assert!(parse_args(args(&["prog.txt", "--", "x"])).is_err());
+    }
+}
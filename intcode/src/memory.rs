@@ -0,0 +1,97 @@
+use crate::Int;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The backing store for an `IntcodeComputer`'s memory.
+/// Addresses that have never been written read as 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Memory<T = isize> {
+    /// A contiguous vector, grown with zeroes when written past the end.
+    Dense(Vec<T>),
+    /// Only the cells that have been written are stored,
+    /// so very high addresses stay cheap.
+    Sparse {
+        cells: HashMap<usize, T>,
+        /// One past the highest address written.
+        len: usize,
+    },
+    /// A program shared with other computers, read in place
+    /// until the first write copies it into `Dense`.
+    Shared(Arc<[T]>),
+}
+
+impl<T: Int> Memory<T> {
+    /// Create a sparse memory populated with the given program.
+    pub fn sparse(program: Vec<T>) -> Memory<T> {
+        Memory::Sparse {
+            len: program.len(),
+            cells: program.into_iter().enumerate().collect(),
+        }
+    }
+
+    pub fn get(&self, idx: usize) -> T {
+        match self {
+            Memory::Dense(vec) => vec.get(idx).copied().unwrap_or_else(T::zero),
+            Memory::Sparse { cells, .. } => cells.get(&idx).copied().unwrap_or_else(T::zero),
+            Memory::Shared(program) => program.get(idx).copied().unwrap_or_else(T::zero),
+        }
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> &mut T {
+        match self {
+            Memory::Dense(vec) => {
+                if idx >= vec.len() {
+                    vec.resize(idx + 1, T::zero());
+                }
+                &mut vec[idx]
+            }
+            Memory::Sparse { cells, len } => {
+                if idx >= *len {
+                    *len = idx + 1;
+                }
+                cells.entry(idx).or_insert_with(T::zero)
+            }
+            Memory::Shared(program) => {
+                *self = Memory::Dense(program.to_vec());
+                self.get_mut(idx)
+            }
+        }
+    }
+
+    /// One past the highest address that's backed by storage.
+    pub fn len(&self) -> usize {
+        match self {
+            Memory::Dense(vec) => vec.len(),
+            Memory::Sparse { len, .. } => *len,
+            Memory::Shared(program) => program.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Flatten memory into a vector, filling unwritten cells with 0.
+    pub fn to_vec(&self) -> Vec<T> {
+        match self {
+            Memory::Dense(vec) => vec.clone(),
+            Memory::Sparse { cells, len } => {
+                let mut vec = vec![T::zero(); *len];
+                for (&idx, &value) in cells {
+                    vec[idx] = value;
+                }
+                vec
+            }
+            Memory::Shared(program) => program.to_vec(),
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for Memory<T> {
+    fn from(program: Vec<T>) -> Memory<T> {
+        Memory::Dense(program)
+    }
+}
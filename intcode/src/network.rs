@@ -0,0 +1,140 @@
+use crate::{Event, IntcodeComputer};
+use anyhow::{format_err, Result};
+
+/// Packets sent here go to the hook set by `Network::on_nat_packet`
+/// instead of another machine.
+pub const NAT_ADDRESS: isize = 255;
+
+/// A group of computers that exchange `(dest, x, y)` packets,
+/// each addressed by its index.
+pub struct Network {
+    machines: Vec<IntcodeComputer>,
+    /// Outputs from each machine that don't make up a whole packet yet.
+    partial: Vec<Vec<isize>>,
+    nat_hook: Option<Box<dyn FnMut(isize, isize)>>,
+}
+
+impl Network {
+    /// Start `size` copies of `program`, each given its address as its first input.
+    pub fn new(program: &[isize], size: usize) -> Network {
+        let machines = (0..size)
+            .map(|addr| {
+                let mut cpu = IntcodeComputer::from(program);
+                cpu.push_input(addr as isize);
+                cpu
+            })
+            .collect();
+
+        Network {
+            machines,
+            partial: vec![Vec::new(); size],
+            nat_hook: None,
+        }
+    }
+
+    /// Queue a packet for the machine at `addr`.
+    pub fn send_to(&mut self, addr: isize, x: isize, y: isize) -> Result<()> {
+        if addr == NAT_ADDRESS {
+            if let Some(hook) = &mut self.nat_hook {
+                hook(x, y);
+            }
+            return Ok(());
+        }
+
+        let cpu = self
+            .machines
+            .get_mut(addr as usize)
+            .filter(|_| addr >= 0)
+            .ok_or_else(|| format_err!("No machine at address {}", addr))?;
+        cpu.push_inputs(vec![x, y]);
+        Ok(())
+    }
+
+    /// Call `hook` with the `x` and `y` of every packet sent to `NAT_ADDRESS`.
+    pub fn on_nat_packet(&mut self, hook: impl FnMut(isize, isize) + 'static) {
+        self.nat_hook = Some(Box::new(hook));
+    }
+
+    /// Give each machine a turn, in address order.
+    /// A machine runs until it reads from an empty queue a second time:
+    /// the first time it's given -1, the second ends its turn.
+    /// Its packets are delivered before the next machine runs.
+    pub fn tick(&mut self) -> Result<()> {
+        for addr in 0..self.machines.len() {
+            for (dest, x, y) in self.run_machine(addr)? {
+                self.send_to(dest, x, y)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn run_machine(&mut self, addr: usize) -> Result<Vec<(isize, isize, isize)>> {
+        let cpu = &mut self.machines[addr];
+        let partial = &mut self.partial[addr];
+        let mut packets = Vec::new();
+        let mut given_idle = false;
+
+        loop {
+            match cpu.execute_buffered()? {
+                Event::HaveOutput(x) => {
+                    partial.push(x);
+                    if let [dest, x, y] = partial[..] {
+                        packets.push((dest, x, y));
+                        partial.clear();
+                    }
+                }
+                Event::RequestingInput if !given_idle => {
+                    given_idle = true;
+                    cpu.push_input(-1);
+                }
+                Event::Breakpoint(_) | Event::SelfModified { .. } => continue,
+                Event::RequestingInput | Event::Halted => break Ok(packets),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Network;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Forwards every packet it gets to the next address,
+    /// except address 1, which forwards to 255.
+    const ROUTER: [isize; 31] = [
+        3, 100, // read our address
+        1002, 100, 254, 101, // dest = addr * 254
+        1001, 101, 1, 101, // dest += 1
+        3, 102, // loop: read x
+        1008, 102, -1, 103, 1005, 103, 10, // back to loop if idle
+        3, 104, // read y
+        4, 101, 4, 102, 4, 104, // send the packet on
+        1105, 1, 10, // back to loop
+        99,
+    ];
+
+    #[test]
+    fn route_to_nat() {
+        let mut network = Network::new(&ROUTER, 2);
+
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&seen);
+        network.on_nat_packet(move |x, y| sink.borrow_mut().push((x, y)));
+
+        network.tick().unwrap();
+        assert!(seen.borrow().is_empty());
+
+        network.send_to(0, 10, 20).unwrap();
+        network.tick().unwrap();
+        assert_eq!(*seen.borrow(), vec![(10, 20)]);
+    }
+
+    #[test]
+    fn send_to_nowhere() {
+        let mut network = Network::new(&ROUTER, 2);
+
+        assert!(network.send_to(2, 0, 0).is_err());
+        assert!(network.send_to(-1, 0, 0).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+/// Every ordering of a slice, generated one at a time by Heap's algorithm.
+/// A slice of `n` items gives `n!` orderings, the first being the slice as given.
+pub struct Permutations<T> {
+    items: Vec<T>,
+    counters: Vec<usize>,
+    i: usize,
+    started: bool,
+}
+
+impl<T: Clone> Permutations<T> {
+    pub fn new(items: &[T]) -> Permutations<T> {
+        Permutations {
+            items: items.to_vec(),
+            counters: vec![0; items.len()],
+            i: 1,
+            started: false,
+        }
+    }
+}
+
+impl<T: Clone> Iterator for Permutations<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        if !self.started {
+            self.started = true;
+            return Some(self.items.clone());
+        }
+
+        while self.i < self.items.len() {
+            let i = self.i;
+            if self.counters[i] < i {
+                // even positions swap with the front, odd ones with their counter
+                let other = match i % 2 {
+                    0 => 0,
+                    _ => self.counters[i],
+                };
+                self.items.swap(other, i);
+                self.counters[i] += 1;
+                self.i = 1;
+                return Some(self.items.clone());
+            } else {
+                self.counters[i] = 0;
+                self.i += 1;
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Permutations;
+    use std::collections::HashSet;
+
+    fn assert_all_distinct(items: &[u8], count: usize) {
+        let perms: Vec<_> = Permutations::new(items).collect();
+
+        assert_eq!(perms.len(), count);
+        assert_eq!(perms.iter().collect::<HashSet<_>>().len(), count);
+        for perm in &perms {
+            let mut sorted = perm.clone();
+            sorted.sort();
+            assert_eq!(sorted, items);
+        }
+    }
+
+    #[test]
+    fn three() {
+        assert_all_distinct(&[1, 2, 3], 6);
+    }
+
+    #[test]
+    fn four() {
+        assert_all_distinct(&[1, 2, 3, 4], 24);
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(
+            Permutations::<u8>::new(&[]).collect::<Vec<_>>(),
+            vec![Vec::<u8>::new()]
+        );
+    }
+}